@@ -0,0 +1,129 @@
+// Auto-clear: take pasted secrets off the clipboard again
+//
+// When a clip with a clearing delay is pasted from history, what the clipboard held
+// before is remembered. Once the delay passes, that is put back (or the clipboard is
+// cleared if it was empty), but only if the clipboard still holds the pasted clip:
+// whatever the user copied since is left alone. Pasting another clearing clip before
+// the first is cleared keeps the original contents, so the first secret never returns.
+use std::sync::{Arc, Mutex};
+
+use crate::capture::{clip_signature, read_clip, write_clip, CapturedClip, ClipboardBackend};
+use crate::storage::{ClipFormat, ClipItem};
+
+struct PendingRestore {
+    /// Signature of the pasted clip as read back from the clipboard
+    pasted: Option<u64>,
+    /// What the clipboard held before the first of a run of clearing pastes
+    previous: Option<CapturedClip>,
+}
+
+/// Identifies a paste to `ClipboardRestorer::restore`
+pub struct RestoreToken(Arc<PendingRestore>);
+
+/// Remembers what clearing pastes replaced, until it is put back
+#[derive(Default)]
+pub struct ClipboardRestorer {
+    pending: Mutex<Option<Arc<PendingRestore>>>,
+}
+
+impl ClipboardRestorer {
+    /// Write a clip like `write_clip`, remembering what the clipboard held before
+    pub fn paste(
+        &self,
+        clipboard: &mut impl ClipboardBackend,
+        item: &ClipItem,
+        formats: &[ClipFormat],
+    ) -> Result<RestoreToken, String> {
+        let current = read_clip(clipboard);
+        write_clip(clipboard, item, formats)?;
+        let pasted = read_clip(clipboard).map(|clip| clip_signature(&clip));
+
+        let mut pending = self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Replacing a clearing paste that is still on the clipboard: restore what was there before it
+        let previous = match pending.take() {
+            Some(earlier) if earlier.pasted.is_some() && earlier.pasted == current.as_ref().map(clip_signature) => {
+                earlier.previous.clone()
+            }
+            _ => current,
+        };
+
+        let restore = Arc::new(PendingRestore { pasted, previous });
+        *pending = Some(restore.clone());
+        Ok(RestoreToken(restore))
+    }
+
+    /// Put back what the paste replaced, if it is the latest clearing paste and the
+    /// clipboard still holds it. Returns whether the clipboard was changed.
+    pub fn restore(&self, clipboard: &mut impl ClipboardBackend, token: &RestoreToken) -> Result<bool, String> {
+        let mut pending = self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !pending.as_ref().is_some_and(|latest| Arc::ptr_eq(latest, &token.0)) {
+            return Ok(false);
+        }
+        *pending = None;
+
+        let current = read_clip(clipboard).map(|clip| clip_signature(&clip));
+        if current.is_none() || current != token.0.pasted {
+            return Ok(false);
+        }
+        match &token.0.previous {
+            Some(previous) => write_clip(clipboard, &previous.item, &previous.formats)?,
+            None => clipboard.write_formats(&[])?,
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::testing::MemoryClipboard;
+    use crate::storage::ContentType;
+
+    fn text(text: &str) -> MemoryClipboard {
+        MemoryClipboard { text: Some(text.to_string()), ..Default::default() }
+    }
+
+    fn secret(text: &str) -> ClipItem {
+        ClipItem::new(text.as_bytes().to_vec(), ContentType::Text)
+    }
+
+    #[test]
+    fn test_restore_previous_contents() {
+        let restorer = ClipboardRestorer::default();
+        let mut clipboard = MemoryClipboard::default();
+        clipboard.copy(MemoryClipboard { html: Some("<b>notes</b>".to_string()), ..text("notes") });
+
+        let token = restorer.paste(&mut clipboard, &secret("hunter2"), &[]).unwrap();
+        assert_eq!(clipboard.text.as_deref(), Some("hunter2"));
+        assert!(restorer.restore(&mut clipboard, &token).unwrap());
+        assert_eq!(clipboard.text.as_deref(), Some("notes"));
+        assert_eq!(clipboard.html.as_deref(), Some("<b>notes</b>"));
+
+        // An empty clipboard is cleared again; a token only restores once
+        let mut clipboard = MemoryClipboard::default();
+        let token = restorer.paste(&mut clipboard, &secret("hunter2"), &[]).unwrap();
+        assert!(restorer.restore(&mut clipboard, &token).unwrap());
+        assert_eq!(clipboard.text, None);
+        assert!(!restorer.restore(&mut clipboard, &token).unwrap());
+    }
+
+    #[test]
+    fn test_later_copies_are_left_alone() {
+        let restorer = ClipboardRestorer::default();
+        let mut clipboard = MemoryClipboard::default();
+        clipboard.copy(text("notes"));
+
+        // Something copied after the paste stays
+        let token = restorer.paste(&mut clipboard, &secret("hunter2"), &[]).unwrap();
+        clipboard.copy(text("new copy"));
+        assert!(!restorer.restore(&mut clipboard, &token).unwrap());
+        assert_eq!(clipboard.text.as_deref(), Some("new copy"));
+
+        // A second clearing paste replaces the first; the first secret never comes back
+        let first = restorer.paste(&mut clipboard, &secret("hunter2"), &[]).unwrap();
+        let second = restorer.paste(&mut clipboard, &secret("correct horse"), &[]).unwrap();
+        assert!(!restorer.restore(&mut clipboard, &first).unwrap());
+        assert!(restorer.restore(&mut clipboard, &second).unwrap());
+        assert_eq!(clipboard.text.as_deref(), Some("new copy"));
+    }
+}
@@ -0,0 +1,238 @@
+// What kind of text a clip holds
+//
+// Text clips are labelled at capture time so search can filter on the label
+// (`kind:url`) and the tray and window can show fitting icons and actions. The checks
+// look at the whole trimmed text: a URL inside a sentence is prose. The first kind
+// that matches wins, in the order of `classify_text`; whatever matches nothing is prose.
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::capture::plain_text;
+use crate::storage::{ClipItem, ContentType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextKind {
+    Url,
+    Email,
+    Phone,
+    /// A hex (`#1e90ff`) or `rgb()`/`rgba()` colour
+    Color,
+    Json,
+    Code,
+    Path,
+    Number,
+    Prose,
+}
+
+impl TextKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextKind::Url => "url",
+            TextKind::Email => "email",
+            TextKind::Phone => "phone",
+            TextKind::Color => "color",
+            TextKind::Json => "json",
+            TextKind::Code => "code",
+            TextKind::Path => "path",
+            TextKind::Number => "number",
+            TextKind::Prose => "prose",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "url" | "link" => Some(TextKind::Url),
+            "email" | "mail" => Some(TextKind::Email),
+            "phone" | "tel" => Some(TextKind::Phone),
+            "color" | "colour" => Some(TextKind::Color),
+            "json" => Some(TextKind::Json),
+            "code" => Some(TextKind::Code),
+            "path" => Some(TextKind::Path),
+            "number" => Some(TextKind::Number),
+            "prose" | "text" => Some(TextKind::Prose),
+            _ => None,
+        }
+    }
+}
+
+static URL: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"(?i)^(?:(?:https?|ftp|file)://|www\.)[^\s/$.?#][^\s]*$"));
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"(?i)^(?:mailto:)?[a-z0-9._%+\-]+@[a-z0-9\-]+(?:\.[a-z0-9\-]+)*\.[a-z]{2,}$"));
+static HEX_COLOR: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"^#([0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$"));
+static RGB_COLOR: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"(?i)^rgba?\(\s*(\d{1,3})\s*[,\s]\s*(\d{1,3})\s*[,\s]\s*(\d{1,3})\s*(?:[,/]\s*(\d*\.?\d+)(%?)\s*)?\)$"));
+/// Integers and decimals, optionally signed, with thousands separators, exponent or percent
+static NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"^[+\-]?(?:\d{1,3}(?:[,_ ]\d{3})+|\d+)?(?:\.\d+)?(?:[eE][+\-]?\d+)?%?$"));
+/// Groups of digits with an optional country code and area code in parentheses
+static PHONE: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"^(?:\+\d{1,3}[\s.\-]?)?(?:\(\d{1,4}\)[\s.\-]?)?\d{1,4}(?:[\s.\-]\d{1,4}){1,5}$|^\+\d{7,15}$"));
+/// Dates and IPv4 addresses, which are groups of digits too
+static NOT_PHONE: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"^\d{4}-\d{2}-\d{2}$|^\d{1,2}[./]\d{1,2}[./]\d{2,4}$|^\d{1,3}(?:\.\d{1,3}){3}$"));
+/// Unix, home-relative, Windows drive and UNC paths
+static PATH: LazyLock<Regex> =
+    LazyLock::new(|| compile(r"^(?:~|\.{1,2})?/[^\s/]|^[A-Za-z]:[\\/]|^\\\\[^\s\\]+\\"));
+
+/// Keywords and punctuation that start or end lines of code in common languages
+static CODE_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    compile(
+        r"(?x)
+    ^\s*(?:fn|pub|let|const|var|function|def|class|import|from|export|return|if|for|while|
+        \#include|\#!|package|func|struct|impl|use|public|private|static|async|await|try|catch|
+        SELECT|INSERT|UPDATE|DELETE|CREATE)\b
+    | [;{}]\s*$
+    | ^\s*(?://|/\*|\*/|\#\s)
+    | =>|->|::|\+\+|&&|\|\||!=|===
+    | ^\s*[\w.]+\(.*\)\s*;?\s*$",
+    )
+});
+
+/// The patterns are compiled once: classification runs on every capture, on tray
+/// rebuilds and over the whole history at startup
+fn compile(pattern: &str) -> Regex {
+    Regex::new(pattern).expect("valid classifier pattern")
+}
+
+/// RGBA of a colour clip's text: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()` or `rgba()`
+pub fn parse_color(text: &str) -> Option<[u8; 4]> {
+    let text = text.trim();
+    if let Some(captures) = HEX_COLOR.captures(text) {
+        let digits = &captures[1];
+        let width = if digits.len() <= 4 { 1 } else { 2 };
+        let mut rgba = [255; 4];
+        for (i, value) in rgba.iter_mut().enumerate().take(digits.len() / width) {
+            let channel = u8::from_str_radix(&digits[i * width..(i + 1) * width], 16).ok()?;
+            // Short forms repeat each digit: #f80 is #ff8800
+            *value = if width == 1 { channel * 17 } else { channel };
+        }
+        return Some(rgba);
+    }
+
+    let captures = RGB_COLOR.captures(text)?;
+    let channel = |i: usize| captures[i].parse::<u8>().ok();
+    let alpha = match (captures.get(4), captures.get(5).map(|m| m.as_str())) {
+        (None, _) => 255,
+        (Some(alpha), Some("%")) => (alpha.as_str().parse::<f64>().ok()? / 100.0 * 255.0).round().clamp(0.0, 255.0) as u8,
+        (Some(alpha), _) => (alpha.as_str().parse::<f64>().ok()? * 255.0).round().clamp(0.0, 255.0) as u8,
+    };
+    Some([channel(1)?, channel(2)?, channel(3)?, alpha])
+}
+
+fn is_json(text: &str) -> bool {
+    (text.starts_with('{') || text.starts_with('[')) && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+fn is_phone(text: &str) -> bool {
+    let digits = text.chars().filter(char::is_ascii_digit).count();
+    (7..=15).contains(&digits) && PHONE.is_match(text) && !NOT_PHONE.is_match(text)
+}
+
+/// At least half the lines look like code. A single line needs two signs of code,
+/// so sentences ending in a semicolon or mentioning `a -> b` stay prose.
+fn is_code(text: &str) -> bool {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    match lines.as_slice() {
+        [] => return false,
+        [line] => return CODE_LINE.find_iter(line).count() >= 2,
+        _ => {}
+    }
+    let code_lines = lines.iter().filter(|line| CODE_LINE.is_match(line)).count();
+    code_lines * 2 >= lines.len()
+}
+
+/// Label `text` with the first kind it matches
+pub fn classify_text(text: &str) -> TextKind {
+    let text = text.trim();
+    let single_line = !text.contains('\n');
+
+    if is_json(text) {
+        TextKind::Json
+    } else if single_line && URL.is_match(text) {
+        TextKind::Url
+    } else if single_line && EMAIL.is_match(text) {
+        TextKind::Email
+    } else if single_line && parse_color(text).is_some() {
+        TextKind::Color
+    } else if single_line && text.chars().any(|c| c.is_ascii_digit()) && NUMBER.is_match(text) {
+        TextKind::Number
+    } else if single_line && is_phone(text) {
+        TextKind::Phone
+    } else if single_line && !text.contains("://") && PATH.is_match(text) {
+        TextKind::Path
+    } else if is_code(text) {
+        TextKind::Code
+    } else {
+        TextKind::Prose
+    }
+}
+
+/// Label of a clip's text; `None` for images and files
+pub fn classify_clip(item: &ClipItem) -> Option<TextKind> {
+    match item.content_type {
+        ContentType::Text | ContentType::Html | ContentType::Rtf => Some(classify_text(&plain_text(item))),
+        ContentType::Image | ContentType::File => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_text() {
+        let cases = [
+            ("https://example.com/docs?page=2#intro", TextKind::Url),
+            ("  www.rust-lang.org\n", TextKind::Url),
+            ("jane.doe+lists@example.co.uk", TextKind::Email),
+            ("mailto:team@example.com", TextKind::Email),
+            ("+1 (415) 555-2671", TextKind::Phone),
+            ("030 1234 5678", TextKind::Phone),
+            ("#1E90FF", TextKind::Color),
+            ("rgba(30, 144, 255, 0.5)", TextKind::Color),
+            ("{\"name\": \"clipman\", \"tags\": [1, 2]}", TextKind::Json),
+            ("[\n  1,\n  2\n]", TextKind::Json),
+            ("fn main() {\n    println!(\"hi\");\n}", TextKind::Code),
+            ("const total = items.reduce((a, b) => a + b, 0);", TextKind::Code),
+            ("/home/me/Documents/report final.pdf", TextKind::Path),
+            ("~/.config/clipman", TextKind::Path),
+            ("C:\\Users\\me\\Desktop", TextKind::Path),
+            ("-1,234,567.89", TextKind::Number),
+            ("4155552671", TextKind::Number),
+            ("6.02e23", TextKind::Number),
+            ("Meeting moved to 2024-01-15 at 10:30", TextKind::Prose),
+            ("2024-01-15", TextKind::Prose),
+            ("192.168.1.10", TextKind::Prose),
+            ("See https://example.com for details", TextKind::Prose),
+            ("Bring snacks; the meeting runs long.", TextKind::Prose),
+            ("{not json", TextKind::Prose),
+            ("#hashtag", TextKind::Prose),
+        ];
+        for (text, kind) in cases {
+            assert_eq!(classify_text(text), kind, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#f80"), Some([255, 136, 0, 255]));
+        assert_eq!(parse_color("#1e90ff80"), Some([30, 144, 255, 128]));
+        assert_eq!(parse_color("rgb(30 144 255 / 50%)"), Some([30, 144, 255, 128]));
+        assert_eq!(parse_color("rgb(300, 0, 0)"), None);
+        assert_eq!(parse_color("#12345"), None);
+    }
+
+    #[test]
+    fn test_classify_clip() {
+        let mut html = ClipItem::new(b"<a href=\"https://example.com\">https://example.com</a>".to_vec(), ContentType::Html);
+        html.plain_text = Some("https://example.com".to_string());
+        assert_eq!(classify_clip(&html), Some(TextKind::Url));
+        assert_eq!(classify_clip(&ClipItem::new(b"/tmp/a.png".to_vec(), ContentType::File)), None);
+        assert_eq!(TextKind::from_name(TextKind::Color.as_str()), Some(TextKind::Color));
+    }
+}
@@ -1,119 +1,52 @@
 use arboard::{Clipboard, ImageData};
-use clipboard_master::{CallbackResult, ClipboardHandler, Master};
+use clipboard_master::{CallbackResult, ClipboardHandler, Master, Shutdown};
+use std::borrow::Cow;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
 use tauri::{AppHandle, Manager, Emitter};
-use uuid::Uuid;
-use chrono::Utc;
 use image::GenericImageView;
 
-use crate::storage::{ClipItem, ContentType};
-
-pub struct ClipboardMonitor {
-    app_handle: AppHandle,
-    last_copied_by_us: Arc<Mutex<Option<String>>>,
+use crate::capture::{self, CaptureEvents, CapturePipeline, ClipboardBackend};
+use crate::monitor::{CaptureMonitor, MonitorStatus};
+#[cfg(target_os = "linux")]
+use crate::selection::{SelectionWatcher, SELECTION_POLL_INTERVAL};
+use crate::source_app::{self, SourceApp};
+use crate::storage::ClipStorage;
+use crate::storage::{ClipFormat, ContentType, FrontendClipItem};
+#[cfg(target_os = "linux")]
+use crate::storage::Selection;
+
+/// How often the clipboard is checked when change events aren't available
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The system clipboard: arboard, plus the platform's own API for RTF and for offering
+/// several formats at once
+pub struct SystemClipboard {
+    clipboard: Clipboard,
+    /// Change events from the clipboard master thread; `None` when polling
+    changes: Option<Receiver<()>>,
+    watcher: Option<Watcher>,
 }
 
-struct Handler {
-    app_handle: AppHandle,
-    last_copied_by_us: Arc<Mutex<Option<String>>>,
-    last_text: Arc<Mutex<String>>,
-    last_image: Arc<Mutex<Option<Vec<u8>>>>,
+/// The clipboard master thread, shut down and joined when the clipboard is dropped
+struct Watcher {
+    /// Sent once the master runs; closed without a value if it couldn't start
+    shutdown: Receiver<Shutdown>,
+    thread: JoinHandle<()>,
 }
 
-impl ClipboardHandler for Handler {
-    fn on_clipboard_change(&mut self) -> CallbackResult {
-        let mut clipboard = match Clipboard::new() {
-            Ok(cb) => cb,
-            Err(e) => {
-                log::error!("Failed to create clipboard instance in handler: {}", e);
-                return CallbackResult::Next;
-            }
-        };
-
-        // Check for text changes
-        if let Ok(text) = clipboard.get_text() {
-            let mut last_text = self.last_text.lock().unwrap();
-            if text != *last_text && !text.is_empty() {
-                // Check if this was copied by us
-                let should_skip = {
-                    if let Ok(last_copied) = self.last_copied_by_us.lock() {
-                        if let Some(ref copied_text) = *last_copied {
-                            if copied_text == &text {
-                                log::info!("⏭️ Skipping self-copied text ({} chars)", text.len());
-                                true
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                };
-
-                if !should_skip {
-                    log::info!("📋 Text clipboard changed: {} chars", text.len());
-                    
-                    let item = ClipItem {
-                        id: Uuid::new_v4().to_string(),
-                        content: text.clone().into_bytes(),
-                        content_type: ContentType::Text,
-                        timestamp: Utc::now().timestamp(),
-                        is_pinned: false,
-                        pin_order: None,
-                    };
-
-                    ClipboardMonitor::save_to_storage(&self.app_handle, item);
-                }
-                
-                *last_text = text;
-            }
-        }
+/// Forwards clipboard change events to the monitor
+struct ChangeNotifier(Sender<()>);
 
-        // Check for image changes
-        if let Ok(image) = clipboard.get_image() {
-            let image_bytes = ClipboardMonitor::image_to_bytes(&image);
-            let mut last_image = self.last_image.lock().unwrap();
-
-            if last_image.as_ref() != Some(&image_bytes) {
-                log::info!("Image clipboard changed: {} bytes", image_bytes.len());
-
-                // Spawn async task for image processing to avoid blocking
-                let app_handle = self.app_handle.clone();
-                let image_bytes_clone = image_bytes.clone();
-                
-                tauri::async_runtime::spawn(async move {
-                    // Check settings for image quality preference
-                    let store_original = if let Some(state) = app_handle.try_state::<crate::AppState>() {
-                        state.settings.get().store_original_image
-                    } else {
-                        false
-                    };
-
-                    let content = if store_original {
-                        ClipboardMonitor::process_full_image(&image_bytes_clone)
-                    } else {
-                        ClipboardMonitor::create_thumbnail(&image_bytes_clone)
-                    };
-
-                    let item = ClipItem {
-                        id: Uuid::new_v4().to_string(),
-                        content,
-                        content_type: ContentType::Image,
-                        timestamp: Utc::now().timestamp(),
-                        is_pinned: false,
-                        pin_order: None,
-                    };
-
-                    ClipboardMonitor::save_to_storage(&app_handle, item);
-                });
-
-                *last_image = Some(image_bytes);
-            }
+impl ClipboardHandler for ChangeNotifier {
+    fn on_clipboard_change(&mut self) -> CallbackResult {
+        match self.0.send(()) {
+            Ok(()) => CallbackResult::Next,
+            // Nobody is listening anymore
+            Err(_) => CallbackResult::Stop,
         }
-
-        CallbackResult::Next
     }
 
     fn on_clipboard_error(&mut self, error: std::io::Error) -> CallbackResult {
@@ -122,184 +55,96 @@ impl ClipboardHandler for Handler {
     }
 }
 
-impl ClipboardMonitor {
-    pub fn new(app_handle: AppHandle, last_copied_by_us: Arc<Mutex<Option<String>>>) -> Self {
-        Self {
-            app_handle,
-            last_copied_by_us,
-        }
+impl SystemClipboard {
+    pub fn new() -> Result<Self, String> {
+        let clipboard = Clipboard::new().map_err(|e| format!("Failed to create clipboard: {}", e))?;
+        Ok(Self { clipboard, changes: None, watcher: None })
     }
 
-    pub fn start(&self) {
-        let app_handle = self.app_handle.clone();
-        let last_copied_by_us = self.last_copied_by_us.clone();
-
-        // Start event-driven monitoring thread
-        std::thread::spawn(move || {
-            log::info!("Clipboard monitoring thread started (event-driven)");
-
-            let handler = Handler {
-                app_handle: app_handle.clone(),
-                last_copied_by_us: last_copied_by_us.clone(),
-                last_text: Arc::new(Mutex::new(String::new())),
-                last_image: Arc::new(Mutex::new(None)),
-            };
-
-            // Start the clipboard master - this blocks until the application exits
-            match Master::new(handler) {
-                Ok(mut master) => {
-                    if let Err(e) = master.run() {
-                        log::error!("Clipboard master failed: {}", e);
-                        log::warn!("Falling back to polling mode...");
-                        Self::start_polling(app_handle, last_copied_by_us);
-                    }
-                }
-                Err(e) => {
-                    log::error!("Failed to create clipboard master: {}", e);
-                    log::warn!("Falling back to polling mode...");
-                    Self::start_polling(app_handle, last_copied_by_us);
+    /// A clipboard that reports changes as the system announces them, falling back
+    /// to polling where it can't
+    pub fn watching() -> Result<Self, String> {
+        let mut clipboard = Self::new()?;
+        let (sender, receiver) = mpsc::channel();
+        let (shutdown_sender, shutdown) = mpsc::channel();
+
+        // The master is created on its own thread, which on Windows owns its message window.
+        // Dropping the sender when it fails switches the monitor to polling.
+        let thread = std::thread::spawn(move || match Master::new(ChangeNotifier(sender)) {
+            Ok(mut master) => {
+                shutdown_sender.send(master.shutdown_channel()).ok();
+                if let Err(e) = master.run() {
+                    log::error!("Clipboard master failed: {}", e);
                 }
             }
+            Err(e) => log::error!("Failed to create clipboard master: {}", e),
         });
+
+        clipboard.changes = Some(receiver);
+        clipboard.watcher = Some(Watcher { shutdown, thread });
+        Ok(clipboard)
     }
 
-    // Fallback polling implementation
-    fn start_polling(app_handle: AppHandle, last_copied_by_us: Arc<Mutex<Option<String>>>) {
-        use std::thread;
-        use std::time::Duration;
+    /// Whether the clipboard can be read, for the permission check
+    pub fn check_access(&mut self) -> Result<(), String> {
+        self.clipboard.get_text().map(|_| ()).map_err(|e| e.to_string())
+    }
 
-        let mut clipboard = match Clipboard::new() {
-            Ok(cb) => cb,
-            Err(e) => {
-                log::error!("Failed to create clipboard instance: {}", e);
-                return;
-            }
-        };
+    fn read_rtf(&mut self) -> Option<String> {
+        #[cfg(any(target_os = "linux", target_os = "windows"))]
+        return rtf_flavour::read();
 
-        let mut last_text = String::new();
-        let mut last_image: Option<Vec<u8>> = None;
-
-        loop {
-            // Check for text changes
-            if let Ok(text) = clipboard.get_text() {
-                if text != last_text && !text.is_empty() {
-                    let should_skip = {
-                        if let Ok(last_copied) = last_copied_by_us.lock() {
-                            if let Some(ref copied_text) = *last_copied {
-                                if copied_text == &text {
-                                    log::info!("⏭️ Skipping self-copied text ({} chars)", text.len());
-                                    true
-                                } else {
-                                    false
-                                }
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    };
-
-                    if !should_skip {
-                        log::info!("📋 Text clipboard changed: {} chars", text.len());
-                        last_text = text.clone();
-
-                        let item = ClipItem {
-                            id: Uuid::new_v4().to_string(),
-                            content: text.into_bytes(),
-                            content_type: ContentType::Text,
-                            timestamp: Utc::now().timestamp(),
-                            is_pinned: false,
-                            pin_order: None,
-                        };
-
-                        Self::save_to_storage(&app_handle, item);
-                    } else {
-                        last_text = text.clone();
-                    }
-                }
-            }
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        None
+    }
 
-            // Check for image changes  
-            if let Ok(image) = clipboard.get_image() {
-                let image_bytes = Self::image_to_bytes(&image);
-
-                if last_image.as_ref() != Some(&image_bytes) {
-                    log::info!("Image clipboard changed: {} bytes", image_bytes.len());
-                    
-                    // Spawn async task for image processing
-                    let app_handle_clone = app_handle.clone();
-                    let image_bytes_clone = image_bytes.clone();
-                    
-                    tauri::async_runtime::spawn(async move {
-                        let store_original = if let Some(state) = app_handle_clone.try_state::<crate::AppState>() {
-                            state.settings.get().store_original_image
-                        } else {
-                            false
-                        };
-
-                        let content = if store_original {
-                            Self::process_full_image(&image_bytes_clone)
-                        } else {
-                            Self::create_thumbnail(&image_bytes_clone)
-                        };
-
-                        let item = ClipItem {
-                            id: Uuid::new_v4().to_string(),
-                            content,
-                            content_type: ContentType::Image,
-                            timestamp: Utc::now().timestamp(),
-                            is_pinned: false,
-                            pin_order: None,
-                        };
-
-                        Self::save_to_storage(&app_handle_clone, item);
-                    });
-                    
-                    last_image = Some(image_bytes);
-                }
-            }
+    fn write_image(&mut self, png: &[u8]) -> Result<(), String> {
+        let img = image::load_from_memory(png).map_err(|e| format!("Failed to decode image: {}", e))?;
+        let (width, height) = img.dimensions();
 
-            thread::sleep(Duration::from_millis(500));
-        }
+        let image_data = ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: Cow::from(img.to_rgba8().into_raw()),
+        };
+        self.clipboard.set_image(image_data).map_err(|e| e.to_string())
     }
 
-    fn save_to_storage(app_handle: &AppHandle, item: ClipItem) {
-        use crate::AppState;
-        use crate::tray::update_tray_menu;
-        use crate::storage::FrontendClipItem;
-
-        let mut item_for_emit = FrontendClipItem::from(item.clone());
+    /// Write only the preferred format, HTML together with the text format
+    fn write_preferred(&mut self, formats: &[ClipFormat]) -> Result<(), String> {
+        let text = formats
+            .iter()
+            .find(|format| format.content_type == ContentType::Text)
+            .map(|format| String::from_utf8_lossy(&format.data).into_owned());
+        let preferred = formats.first().ok_or("Nothing to copy")?;
+        let content = String::from_utf8_lossy(&preferred.data);
+
+        match preferred.content_type {
+            ContentType::Html => self.clipboard.set_html(content.as_ref(), text.as_deref()).map_err(|e| e.to_string()),
+            // arboard has no RTF; apps still get the text
+            ContentType::Rtf => self.clipboard.set_text(text.unwrap_or_default()).map_err(|e| e.to_string()),
+            ContentType::Image => self.write_image(&preferred.data),
+            ContentType::File => self
+                .clipboard
+                .set()
+                .file_list(&capture::parse_file_list(&preferred.data))
+                .map_err(|e| e.to_string()),
+            ContentType::Text => self.clipboard.set_text(content).map_err(|e| e.to_string()),
+        }
+    }
 
-        let state = app_handle.state::<AppState>();
-        let max_history_items = state.settings.get().max_history_items;
+    /// Offer all formats through the platform's clipboard API
+    fn write_all(formats: &[ClipFormat]) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        return crate::x11_clipboard::serve(crate::x11_clipboard::format_targets(formats));
 
-        let result = {
-            let storage = state.storage.lock().unwrap_or_else(|poisoned| {
-                log::warn!("⚠️ Recovered from poisoned lock in clipboard monitor");
-                poisoned.into_inner()
-            });
-            storage.insert(&item, max_history_items)
-        };
+        #[cfg(target_os = "windows")]
+        return crate::windows_clipboard::write_formats(formats);
 
-        match result {
-            Ok(existing_id) => {
-                if let Some(id) = existing_id {
-                    // It was a duplicate, update the emitted item to use the existing ID
-                    // and current timestamp so frontend moves it to top
-                    item_for_emit.id = id;
-                    item_for_emit.timestamp = item.timestamp;
-                    log::debug!("Updated existing item {} timestamp", item_for_emit.id);
-                }
-                
-                app_handle.emit("clipboard-changed", &item_for_emit).ok();
-                log::debug!("Updating tray menu...");
-                update_tray_menu(app_handle);
-                log::debug!("Clipboard item saved/updated and tray updated");
-            }
-            Err(e) => {
-                log::error!("Failed to save clipboard item: {}", e);
-            }
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            let _ = formats;
+            Err("Offering several clipboard formats at once is not supported on this platform".to_string())
         }
     }
 
@@ -326,62 +171,296 @@ impl ClipboardMonitor {
 
         png_bytes
     }
+}
 
-    fn create_thumbnail(image_bytes: &[u8]) -> Vec<u8> {
-        const THUMBNAIL_SIZE: u32 = 256;
-        
-        match image::load_from_memory(image_bytes) {
-            Ok(img) => {
-                let thumbnail = img.resize(
-                    THUMBNAIL_SIZE,
-                    THUMBNAIL_SIZE,
-                    image::imageops::FilterType::Lanczos3,
-                );
-                
-                let mut buffer = Vec::new();
-                if thumbnail.write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png).is_ok() {
-                    log::info!("Created thumbnail: {}x{} -> {}x{}, {} bytes",
-                               img.width(), img.height(), thumbnail.width(), thumbnail.height(), buffer.len());
-                    buffer
-                } else {
-                    log::error!("Failed to encode thumbnail. Returning original bytes.");
-                    image_bytes.to_vec()
-                }
+impl Drop for SystemClipboard {
+    fn drop(&mut self) {
+        if let Some(watcher) = self.watcher.take() {
+            if let Ok(shutdown) = watcher.shutdown.recv() {
+                shutdown.signal();
+            }
+            if watcher.thread.join().is_err() {
+                log::error!("Clipboard master thread panicked");
             }
-            Err(e) => {
-                log::warn!("Failed to decode image for thumbnail: {}. Returning original bytes.", e);
-                image_bytes.to_vec()
-            },
         }
     }
+}
 
-    fn process_full_image(image_bytes: &[u8]) -> Vec<u8> {
-        const MAX_SIZE: u32 = 2048;
-        
-        match image::load_from_memory(image_bytes) {
-            Ok(img) => {
-                let (w, h) = img.dimensions();
-                
-                let final_img = if w > MAX_SIZE || h > MAX_SIZE {
-                    img.resize(MAX_SIZE, MAX_SIZE, image::imageops::FilterType::Lanczos3)
-                } else {
-                    img
-                };
-                
-                let mut buffer = Vec::new();
-                if final_img.write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png).is_ok() {
-                    log::info!("Stored high-quality image: {}x{} -> {} bytes", 
-                               final_img.width(), final_img.height(), buffer.len());
-                    buffer
-                } else {
-                    log::error!("Failed to encode high-quality image. Falling back to thumbnail.");
-                    Self::create_thumbnail(image_bytes)
-                }
+impl ClipboardBackend for SystemClipboard {
+    fn read_formats(&mut self) -> Vec<ClipFormat> {
+        let format = |content_type, data: Vec<u8>| ClipFormat { content_type, data };
+        let mut formats = Vec::new();
+        if let Ok(paths) = self.clipboard.get().file_list() {
+            formats.push(format(ContentType::File, capture::format_file_list(&paths)));
+        }
+        if let Ok(html) = self.clipboard.get().html() {
+            formats.push(format(ContentType::Html, html.into_bytes()));
+        }
+        if let Some(rtf) = self.read_rtf() {
+            formats.push(format(ContentType::Rtf, rtf.into_bytes()));
+        }
+        if let Ok(image) = self.clipboard.get_image() {
+            formats.push(format(ContentType::Image, Self::image_to_bytes(&image)));
+        }
+        if let Ok(text) = self.clipboard.get_text() {
+            formats.push(format(ContentType::Text, text.into_bytes()));
+        }
+        formats
+    }
+
+    fn write_formats(&mut self, formats: &[ClipFormat]) -> Result<(), String> {
+        if formats.is_empty() {
+            return self.clipboard.clear().map_err(|e| e.to_string());
+        }
+
+        // arboard offers one format, or HTML with its text; anything else needs the platform API
+        let arboard_can_write = match formats {
+            [_] => true,
+            [first, second] => first.content_type == ContentType::Html && second.content_type == ContentType::Text,
+            _ => false,
+        };
+        if !arboard_can_write {
+            match Self::write_all(formats) {
+                Ok(()) => return Ok(()),
+                Err(e) => log::warn!("⚠️ Can't offer all formats, copying the preferred one: {}", e),
+            }
+        }
+
+        self.write_preferred(formats)
+    }
+
+    fn is_concealed(&mut self) -> bool {
+        #[cfg(target_os = "linux")]
+        return crate::x11_clipboard::is_concealed(Selection::Clipboard).unwrap_or_else(|e| {
+            log::debug!("Failed to read the password manager hint: {}", e);
+            false
+        });
+
+        #[cfg(target_os = "windows")]
+        return crate::windows_clipboard::is_concealed();
+
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        false
+    }
+
+    fn source_app(&mut self) -> Option<SourceApp> {
+        source_app::active_app()
+    }
+
+    /// Served from a thread of ClipMan's until another app copies something. This owns
+    /// the X11 selection only: native Wayland apps don't see it, since keeping their
+    /// clipboard would need the wlr data-control protocol, which isn't implemented.
+    #[cfg(target_os = "linux")]
+    fn take_over(&mut self, formats: &[ClipFormat]) -> Result<(), String> {
+        crate::x11_clipboard::serve(crate::x11_clipboard::format_targets(formats))
+    }
+
+    fn wait_for_change(&mut self, timeout: Duration) -> bool {
+        let Some(changes) = &self.changes else {
+            // Polling: look again once the interval is up
+            std::thread::sleep(timeout);
+            return true;
+        };
+
+        match changes.recv_timeout(timeout) {
+            Ok(()) => {
+                // One look covers changes announced in the meantime
+                while changes.try_recv().is_ok() {}
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => {
+                log::warn!("Falling back to polling mode...");
+                self.changes = None;
+                true
             }
-            Err(e) => {
-                log::warn!("Failed to decode image for high-quality storage: {}. Falling back to thumbnail.", e);
-                Self::create_thumbnail(image_bytes)
-            },
         }
     }
 }
+
+/// The X11 PRIMARY selection: selected text, pasted with a middle click. It doesn't
+/// announce changes, so it is polled; see selection.rs.
+#[cfg(target_os = "linux")]
+pub struct PrimarySelection {
+    clipboard: Clipboard,
+}
+
+#[cfg(target_os = "linux")]
+impl PrimarySelection {
+    pub fn new() -> Result<Self, String> {
+        let clipboard = Clipboard::new().map_err(|e| format!("Failed to create clipboard: {}", e))?;
+        Ok(Self { clipboard })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ClipboardBackend for PrimarySelection {
+    fn read_formats(&mut self) -> Vec<ClipFormat> {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+
+        let format = |content_type, data: String| ClipFormat { content_type, data: data.into_bytes() };
+        let mut formats = Vec::new();
+        if let Ok(html) = self.clipboard.get().clipboard(LinuxClipboardKind::Primary).html() {
+            formats.push(format(ContentType::Html, html));
+        }
+        if let Ok(text) = self.clipboard.get().clipboard(LinuxClipboardKind::Primary).text() {
+            formats.push(format(ContentType::Text, text));
+        }
+        formats
+    }
+
+    /// Selections are text: only the text format is written
+    fn write_formats(&mut self, formats: &[ClipFormat]) -> Result<(), String> {
+        use arboard::{ClearExtLinux, LinuxClipboardKind, SetExtLinux};
+
+        let text = formats
+            .iter()
+            .find(|format| format.content_type == ContentType::Text)
+            .map(|format| String::from_utf8_lossy(&format.data).into_owned());
+        let written = match text {
+            Some(text) => self.clipboard.set().clipboard(LinuxClipboardKind::Primary).text(text),
+            None => self.clipboard.clear_with().clipboard(LinuxClipboardKind::Primary),
+        };
+        written.map_err(|e| e.to_string())
+    }
+
+    fn wait_for_change(&mut self, timeout: Duration) -> bool {
+        std::thread::sleep(timeout);
+        true
+    }
+
+    fn is_concealed(&mut self) -> bool {
+        crate::x11_clipboard::is_concealed(Selection::Primary).unwrap_or_else(|e| {
+            log::debug!("Failed to read the password manager hint of the selection: {}", e);
+            false
+        })
+    }
+
+    fn source_app(&mut self) -> Option<SourceApp> {
+        source_app::active_app()
+    }
+
+    fn selection(&self) -> Selection {
+        Selection::Primary
+    }
+}
+
+/// RTF flavour of the system clipboard, which arboard doesn't expose
+#[cfg(target_os = "linux")]
+mod rtf_flavour {
+    use crate::x11_clipboard::{self, RTF_TARGETS};
+
+    pub fn read() -> Option<String> {
+        x11_clipboard::read_string(RTF_TARGETS).unwrap_or_else(|e| {
+            log::debug!("Failed to read RTF from the X11 clipboard: {}", e);
+            None
+        })
+    }
+}
+
+/// RTF flavour of the system clipboard, which arboard doesn't expose
+#[cfg(target_os = "windows")]
+mod rtf_flavour {
+    pub use crate::windows_clipboard::read_rtf as read;
+}
+
+/// Reports captured clips to the frontend and refreshes the tray
+impl CaptureEvents for AppHandle {
+    fn clip_saved(&self, item: &FrontendClipItem, matched_searches: &[String]) {
+        self.emit("clipboard-changed", item).ok();
+
+        // Let open smart collections refresh (the tray is rebuilt below anyway)
+        if !matched_searches.is_empty() {
+            self.emit("saved-search-matched", serde_json::json!({
+                "clipId": item.id,
+                "searchIds": matched_searches,
+            })).ok();
+        }
+
+        log::debug!("Updating tray menu...");
+        crate::tray::update_tray_menu(self);
+        log::debug!("Clipboard item saved/updated and tray updated");
+    }
+}
+
+/// Clipboard monitoring for the app: captured clips go to storage, the frontend and the tray
+pub struct ClipboardMonitor {
+    app_handle: AppHandle,
+    monitor: CaptureMonitor<AppHandle>,
+    /// Watches the PRIMARY selection while it is captured or synced (Linux)
+    selection: CaptureMonitor<AppHandle>,
+}
+
+impl ClipboardMonitor {
+    pub fn new(
+        app_handle: AppHandle,
+        storage: Arc<Mutex<ClipStorage>>,
+        last_copied_by_us: Arc<Mutex<Option<String>>>,
+    ) -> Self {
+        let pipeline = CapturePipeline::new(storage.clone(), last_copied_by_us.clone(), app_handle.clone());
+        let selection_pipeline = CapturePipeline::new(storage, last_copied_by_us, app_handle.clone());
+        Self {
+            app_handle,
+            monitor: CaptureMonitor::new(pipeline),
+            selection: CaptureMonitor::new(selection_pipeline),
+        }
+    }
+
+    /// Start monitoring, unless already running
+    pub fn start(&mut self) {
+        let app_handle = self.app_handle.clone();
+        let options = move || app_handle.state::<crate::AppState>().settings.get().capture_options();
+        self.monitor.start(SystemClipboard::watching, options, POLL_INTERVAL);
+        self.start_selection();
+    }
+
+    /// Watch the PRIMARY selection if the settings ask for it
+    fn start_selection(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            let selection_options = self.app_handle.state::<crate::AppState>().settings.get().selection_options();
+            if !selection_options.is_enabled() {
+                return;
+            }
+
+            let clipboard_pipeline = self.monitor.pipeline();
+            let connect = move || {
+                Ok(SelectionWatcher::new(PrimarySelection::new()?, SystemClipboard::new()?, clipboard_pipeline, selection_options))
+            };
+            let app_handle = self.app_handle.clone();
+            let options = move || app_handle.state::<crate::AppState>().settings.get().capture_options();
+            self.selection.start(connect, options, SELECTION_POLL_INTERVAL);
+        }
+    }
+
+    /// Apply changed selection settings
+    pub fn restart_selection(&mut self) {
+        self.selection.stop();
+        self.start_selection();
+    }
+
+    /// Stop monitoring and wait until the monitoring threads have finished
+    pub fn stop(&mut self) {
+        self.selection.stop();
+        self.monitor.stop();
+    }
+
+    /// Change the clipboard without the change being captured
+    pub fn without_capture<T>(&self, clipboard: &mut SystemClipboard, write: impl FnOnce(&mut SystemClipboard) -> T) -> T {
+        self.monitor.without_capture(clipboard, write)
+    }
+
+    pub fn pause(&self) {
+        self.monitor.pause();
+        self.selection.pause();
+    }
+
+    pub fn resume(&self) {
+        self.monitor.resume();
+        self.selection.resume();
+    }
+
+    pub fn status(&self) -> MonitorStatus {
+        self.monitor.status()
+    }
+}
@@ -3,8 +3,14 @@ use tauri::{AppHandle, Manager, State, Emitter};
 use tauri_plugin_notification::NotificationExt;
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
 
-use crate::storage::{FrontendClipItem, ContentType};
+use crate::storage::{FrontendClipItem, FrontendSearchResult, ContentType, PinGroup, ClipRevision, SavedSearch, SortMode};
+use crate::query::Query;
+use crate::autoclear::RestoreToken;
+use crate::capture_rules::{check_capture_rules, CaptureRule, RuleVerdict};
+use crate::clipboard::ClipboardMonitor;
+use crate::monitor::MonitorStatus;
 use crate::settings::Settings;
+use crate::transform::{apply_transforms, TransformPreview, TransformRule};
 use crate::tray::update_tray_menu;
 use crate::{AppState, safe_lock, migration};
 
@@ -12,13 +18,15 @@ use crate::{AppState, safe_lock, migration};
 pub async fn get_clipboard_history(
     state: State<'_, AppState>,
     limit: Option<usize>,
+    sort: Option<SortMode>,
 ) -> Result<Vec<FrontendClipItem>, String> {
     let storage = state.storage.clone();
     let limit = limit.unwrap_or(100);
+    let sort = sort.unwrap_or_default();
 
     tauri::async_runtime::spawn_blocking(move || {
         let storage = safe_lock(&storage);
-        let items = storage.get_recent(limit).map_err(|e| e.to_string())?;
+        let items = storage.get_recent(limit, sort).map_err(|e| e.to_string())?;
         Ok(items.into_iter().map(FrontendClipItem::from).collect())
     })
     .await
@@ -29,13 +37,113 @@ pub async fn get_clipboard_history(
 pub async fn search_clips(
     state: State<'_, AppState>,
     query: String,
-) -> Result<Vec<FrontendClipItem>, String> {
+    sort: Option<SortMode>,
+) -> Result<Vec<FrontendSearchResult>, String> {
     let storage = state.storage.clone();
+    let sort = sort.unwrap_or(SortMode::Relevance);
+    // Syntax errors are reported before touching the database
+    let query = Query::parse(&query).map_err(|e| e.to_string())?;
     
     tauri::async_runtime::spawn_blocking(move || {
         let storage = safe_lock(&storage);
-        let items = storage.search(&query).map_err(|e| e.to_string())?;
-        Ok(items.into_iter().map(FrontendClipItem::from).collect())
+        let results = storage.search(&query, sort, chrono::Utc::now().timestamp())
+            .map_err(|e| e.to_string())?;
+        Ok(results.into_iter().map(FrontendSearchResult::from).collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_saved_searches(state: State<'_, AppState>) -> Result<Vec<SavedSearch>, String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.get_saved_searches().map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Create a saved search, or update it when `id` is given
+#[tauri::command]
+pub async fn save_search(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: Option<String>,
+    name: String,
+    query: String,
+    sort: Option<SortMode>,
+    show_in_tray: bool,
+) -> Result<SavedSearch, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Saved search name cannot be empty".to_string());
+    }
+    // Reject queries that would never run
+    Query::parse(&query).map_err(|e| e.to_string())?;
+
+    let search = SavedSearch {
+        id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        name,
+        query: query.trim().to_string(),
+        sort: sort.unwrap_or(SortMode::Relevance),
+        show_in_tray,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    let storage = state.storage.clone();
+    let saved = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.save_search(&search).map_err(|e| e.to_string())?;
+        storage.get_saved_search(&search.id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Saved search not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    update_tray_menu(&app);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn delete_saved_search(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.delete_saved_search(&id).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    update_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn run_saved_search(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<FrontendSearchResult>, String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        let search = storage.get_saved_search(&id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Saved search not found".to_string())?;
+        let query = Query::parse(&search.query).map_err(|e| e.to_string())?;
+
+        let results = storage.search(&query, search.sort, chrono::Utc::now().timestamp())
+            .map_err(|e| e.to_string())?;
+        Ok(results.into_iter().map(FrontendSearchResult::from).collect())
     })
     .await
     .map_err(|e| e.to_string())?
@@ -61,6 +169,397 @@ pub async fn toggle_pin(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn move_pin(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    new_index: usize,
+) -> Result<(), String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.move_pin(&id, new_index).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    update_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_pin_groups(
+    state: State<'_, AppState>,
+) -> Result<Vec<PinGroup>, String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.get_pin_groups().map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn create_pin_group(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<PinGroup, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Pin group name cannot be empty".to_string());
+    }
+    let storage = state.storage.clone();
+
+    // Empty groups are not shown in the tray, so no menu rebuild is needed here
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.create_pin_group(&name).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn rename_pin_group(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    name: String,
+) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Pin group name cannot be empty".to_string());
+    }
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.rename_pin_group(&id, &name).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Pin group not found".to_string(),
+            e => e.to_string(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    update_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_pin_group(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.delete_pin_group(&id).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    update_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn assign_pin_group(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    group_id: Option<String>,
+) -> Result<(), String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.assign_pin_group(&id, group_id.as_deref()).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Clip or pin group not found".to_string(),
+            e => e.to_string(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    update_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_clip_content(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    content: String,
+) -> Result<FrontendClipItem, String> {
+    use chrono::Utc;
+
+    let storage = state.storage.clone();
+    let detect_sensitive = state.settings.get().detect_sensitive;
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        let existing = storage.get_by_id(&id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())?;
+
+        if !existing.content_type.is_editable() {
+            return Err("Only text, HTML and file clips can be edited".to_string());
+        }
+
+        let item = storage.update_clip_content(&id, content.as_bytes(), Utc::now().timestamp())
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())?;
+
+        // The edit may have changed what the text holds
+        let item = storage.set_text_kind(&id, crate::classify::classify_clip(&item))
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())?;
+
+        // A secret pasted into the clip is flagged like a captured one
+        if detect_sensitive && !item.sensitive && !crate::sensitive::detect_sensitive(&crate::capture::plain_text(&item)).is_empty() {
+            return storage.set_sensitive(&id, true)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "Clip not found".to_string());
+        }
+        Ok(item)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    update_tray_menu(&app);
+    Ok(frontend_item)
+}
+
+#[tauri::command]
+pub async fn update_clip_details(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    title: Option<String>,
+    note: Option<String>,
+) -> Result<FrontendClipItem, String> {
+    let storage = state.storage.clone();
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.update_clip_details(&id, title.as_deref(), note.as_deref())
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    update_tray_menu(&app);
+    Ok(frontend_item)
+}
+
+#[tauri::command]
+pub async fn set_clip_tags(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    tags: Vec<String>,
+) -> Result<FrontendClipItem, String> {
+    let storage = state.storage.clone();
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.set_tags(&id, &tags)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    Ok(frontend_item)
+}
+
+#[tauri::command]
+pub async fn get_all_tags(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.get_all_tags().map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn set_clip_expiry(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    expires_at: Option<i64>,
+) -> Result<FrontendClipItem, String> {
+    let storage = state.storage.clone();
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.set_expiry(&id, expires_at)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    Ok(frontend_item)
+}
+
+/// Flag a clip as sensitive by hand, or clear a flag set by the detectors
+#[tauri::command]
+pub async fn set_clip_sensitive(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    sensitive: bool,
+) -> Result<FrontendClipItem, String> {
+    let storage = state.storage.clone();
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.set_sensitive(&id, sensitive)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    update_tray_menu(&app);
+    Ok(frontend_item)
+}
+
+/// Set or remove the delay after which a paste of the clip is cleared from the clipboard
+#[tauri::command]
+pub async fn set_clip_clear_after(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    clear_after_secs: Option<u32>,
+) -> Result<FrontendClipItem, String> {
+    let storage = state.storage.clone();
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.set_clear_after(&id, clear_after_secs.filter(|secs| *secs > 0))
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    Ok(frontend_item)
+}
+
+#[tauri::command]
+pub async fn set_clip_paste_limit(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    max_pastes: Option<u32>,
+) -> Result<FrontendClipItem, String> {
+    let storage = state.storage.clone();
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.set_paste_limit(&id, max_pastes.filter(|n| *n > 0))
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clip not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    Ok(frontend_item)
+}
+
+#[tauri::command]
+pub async fn list_revisions(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<ClipRevision>, String> {
+    let storage = state.storage.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.list_revisions(&id).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn revert_revision(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    revision_id: i64,
+) -> Result<FrontendClipItem, String> {
+    use chrono::Utc;
+
+    let storage = state.storage.clone();
+
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let storage = safe_lock(&storage);
+        storage.revert_revision(&id, revision_id, Utc::now().timestamp())
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Revision not found".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let frontend_item = FrontendClipItem::from(item);
+    if let Err(e) = app.emit("clip-updated", &frontend_item) {
+        log::error!("Failed to emit clip-updated event: {}", e);
+    }
+
+    update_tray_menu(&app);
+    Ok(frontend_item)
+}
+
 #[tauri::command]
 pub async fn delete_clip(
     app: AppHandle,
@@ -87,18 +586,69 @@ pub async fn get_settings(
     Ok(state.settings.get())
 }
 
+/// Show what the transform rules make of `text`. Uses `rules` when given, so unsaved
+/// edits can be previewed, otherwise the saved rules.
+#[tauri::command]
+pub async fn preview_transforms(
+    state: State<'_, AppState>,
+    text: String,
+    rules: Option<Vec<TransformRule>>,
+) -> Result<TransformPreview, String> {
+    let rules = rules.unwrap_or_else(|| state.settings.get().transform_rules);
+    Ok(apply_transforms(&text, &rules))
+}
+
+/// Show what the capture rules would do with `text`. Uses `rules` when given, so a rule
+/// can be tried before it is enabled, otherwise the saved rules.
+#[tauri::command]
+pub async fn test_capture_rules(
+    state: State<'_, AppState>,
+    text: String,
+    rules: Option<Vec<CaptureRule>>,
+) -> Result<RuleVerdict, String> {
+    let rules = rules.unwrap_or_else(|| state.settings.get().capture_rules);
+    Ok(check_capture_rules(&text, &rules))
+}
+
+/// Run `f` on the clipboard monitor and report its status afterwards
+fn with_monitor(app: &AppHandle, state: &AppState, f: impl FnOnce(&ClipboardMonitor)) -> Result<MonitorStatus, String> {
+    let status = {
+        let monitor = safe_lock(&state.monitor);
+        let monitor = monitor.as_ref().ok_or("Clipboard monitoring is not set up")?;
+        f(monitor);
+        monitor.status()
+    };
+    app.emit("monitoring-status-changed", status).ok();
+    Ok(status)
+}
+
+/// Stop capturing clips until `resume_monitoring`. What is copied meanwhile is never captured.
+#[tauri::command]
+pub async fn pause_monitoring(app: AppHandle, state: State<'_, AppState>) -> Result<MonitorStatus, String> {
+    log::info!("⏸️ Clipboard monitoring paused");
+    with_monitor(&app, &state, ClipboardMonitor::pause)
+}
+
+#[tauri::command]
+pub async fn resume_monitoring(app: AppHandle, state: State<'_, AppState>) -> Result<MonitorStatus, String> {
+    log::info!("▶️ Clipboard monitoring resumed");
+    with_monitor(&app, &state, ClipboardMonitor::resume)
+}
+
+#[tauri::command]
+pub async fn monitoring_status(state: State<'_, AppState>) -> Result<MonitorStatus, String> {
+    let monitor = safe_lock(&state.monitor);
+    Ok(monitor.as_ref().map_or(MonitorStatus::Stopped, ClipboardMonitor::status))
+}
+
 #[tauri::command]
 pub async fn check_clipboard_permission() -> Result<String, String> {
-    use arboard::Clipboard;
+    use crate::clipboard::SystemClipboard;
 
-    match Clipboard::new() {
-        Ok(mut clipboard) => {
-            match clipboard.get_text() {
-                Ok(_) => Ok("granted".to_string()),
-                Err(e) => Ok(format!("denied: {}", e)),
-            }
-        }
-        Err(e) => Err(format!("Failed to create clipboard: {}", e)),
+    let mut clipboard = SystemClipboard::new()?;
+    match clipboard.check_access() {
+        Ok(()) => Ok("granted".to_string()),
+        Err(e) => Ok(format!("denied: {}", e)),
     }
 }
 
@@ -148,15 +698,36 @@ pub async fn clear_non_pinned_history(
     Ok(())
 }
 
+/// Put back what an auto-cleared paste replaced once `delay` passes, unless something
+/// else was copied meanwhile. The restored contents aren't captured again.
+fn schedule_clipboard_restore(app: AppHandle, delay: std::time::Duration, token: RestoreToken) {
+    use crate::clipboard::SystemClipboard;
+
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+
+        let state = app.state::<AppState>();
+        let restore = |clipboard: &mut SystemClipboard| state.clipboard_restorer.restore(clipboard, &token);
+        let result = SystemClipboard::new().and_then(|mut clipboard| match safe_lock(&state.monitor).as_ref() {
+            Some(monitor) => monitor.without_capture(&mut clipboard, restore),
+            None => restore(&mut clipboard),
+        });
+
+        match result {
+            Ok(true) => log::info!("🧹 Cleared pasted clip from the clipboard"),
+            Ok(false) => log::debug!("Clipboard changed since the paste, leaving it as is"),
+            Err(e) => log::warn!("⚠️ Failed to clear pasted clip from the clipboard: {}", e),
+        }
+    });
+}
+
 /// Copy a clip item to system clipboard (unified function)
 pub async fn copy_clip_to_clipboard_internal(
     app: &AppHandle,
     clip_id: &str,
     show_notification: bool,
 ) -> Result<(), String> {
-    use arboard::{Clipboard, ImageData};
-    use image::GenericImageView;
-    use std::borrow::Cow;
+    use crate::clipboard::SystemClipboard;
     use chrono::Utc;
     use crate::storage::FrontendClipItem;
 
@@ -164,23 +735,26 @@ pub async fn copy_clip_to_clipboard_internal(
     let storage = state.storage.clone();
     let clip_id_for_fetch = clip_id.to_string();
 
-    // Fetch item using get_by_id for efficiency
-    let item = tauri::async_runtime::spawn_blocking(move || {
+    // Fetch item using get_by_id for efficiency, with the alternative formats it was captured with
+    let (item, formats) = tauri::async_runtime::spawn_blocking(move || {
         let storage = safe_lock(&storage);
-        storage.get_by_id(&clip_id_for_fetch)
+        let item = storage.get_by_id(&clip_id_for_fetch)?;
+        let formats = storage.get_formats(&clip_id_for_fetch)?;
+        Ok::<_, rusqlite::Error>(item.map(|item| (item, formats)))
     })
     .await
     .map_err(|e| e.to_string())?
     .map_err(|e| e.to_string())?
     .ok_or_else(|| "Clip not found".to_string())?;
 
-    // Update timestamp to move item to top of recent list
+    // Update timestamp to move item to top of recent list, and count the use for frecency
     let new_timestamp = Utc::now().timestamp();
     let storage = state.storage.clone();
     let clip_id_for_update = clip_id.to_string();
     tauri::async_runtime::spawn_blocking(move || {
         let storage = safe_lock(&storage);
-        storage.update_timestamp(&clip_id_for_update, new_timestamp)
+        storage.update_timestamp(&clip_id_for_update, new_timestamp)?;
+        storage.record_use(&clip_id_for_update, new_timestamp)
     })
     .await
     .map_err(|e| e.to_string())?
@@ -189,6 +763,8 @@ pub async fn copy_clip_to_clipboard_internal(
     // Emit event to notify frontend about the timestamp update
     let mut updated_item = item.clone();
     updated_item.timestamp = new_timestamp;
+    updated_item.copy_count += 1;
+    updated_item.last_used_at = Some(new_timestamp);
     let frontend_item = FrontendClipItem::from(updated_item);
     if let Err(e) = app.emit("clipboard-changed", &frontend_item) {
         log::error!("Failed to emit clipboard-changed event: {}", e);
@@ -198,91 +774,72 @@ pub async fn copy_clip_to_clipboard_internal(
     update_tray_menu(app);
 
     // Copy to system clipboard
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    let mut clipboard = SystemClipboard::new()?;
 
-    match item.content_type {
-        ContentType::Text => {
-            let text = String::from_utf8_lossy(&item.content).to_string();
+    // Every captured format goes back together, so the paste target picks the best one;
+    // file clips are restored as files so they can be pasted into a file manager
+    let text = crate::capture::plain_text(&item);
 
-            // Mark as self-copied to prevent re-capture
-            {
-                let mut last_copied = safe_lock(&state.last_copied_by_us);
-                *last_copied = Some(text.clone());
-            }
+    // Mark as self-copied to prevent re-capture
+    if !text.is_empty() {
+        let mut last_copied = safe_lock(&state.last_copied_by_us);
+        *last_copied = Some(text.clone());
+    }
 
-            clipboard.set_text(text.clone()).map_err(|e| e.to_string())?;
-            log::info!("✅ Copied text to clipboard: {} chars", text.len());
+    // Secrets are taken off the clipboard again after a while
+    let clear_after = item.clear_after_secs
+        .or_else(|| state.settings.get().clear_sensitive_after_secs.filter(|_| item.sensitive));
+    match clear_after {
+        Some(secs) => {
+            let token = state.clipboard_restorer.paste(&mut clipboard, &item, &formats)?;
+            schedule_clipboard_restore(app.clone(), std::time::Duration::from_secs(secs.into()), token);
+        }
+        None => crate::capture::write_clip(&mut clipboard, &item, &formats)?,
+    }
+    log::info!(
+        "✅ Copied {} to clipboard: {} chars, {} alternative formats",
+        item.content_type.to_string(),
+        text.len(),
+        formats.len()
+    );
 
-            if show_notification {
-                #[cfg(not(target_os = "linux"))]
-                let _ = app.notification()
-                    .builder()
-                    .title("已复制")
-                    .body("文本已复制到剪贴板")
-                    .show();
-            }
+    if show_notification {
+        #[cfg(not(target_os = "linux"))]
+        let _ = app.notification()
+            .builder()
+            .title("已复制")
+            .body(match item.content_type {
+                ContentType::Text => "文本已复制到剪贴板",
+                ContentType::File => "文件已复制到剪贴板",
+                ContentType::Image => "图片已复制到剪贴板",
+                _ => "富文本已复制到剪贴板",
+            })
+            .show();
+    }
 
-            // Clear marker after 2 seconds
-            let last_copied_by_us = state.last_copied_by_us.clone();
-            std::thread::spawn(move || {
-                std::thread::sleep(std::time::Duration::from_secs(2));
-                let mut last_copied = safe_lock(&last_copied_by_us);
-                *last_copied = None;
-                log::debug!("🧹 Cleared self-copy marker");
-            });
-        }
-        ContentType::Image => {
-            let img = image::load_from_memory(&item.content)
-                .map_err(|e| format!("Failed to decode image: {}", e))?;
-            
-            let (width, height) = img.dimensions();
-            let rgba_bytes = img.to_rgba8().into_raw();
-
-            let image_data = ImageData {
-                width: width as usize,
-                height: height as usize,
-                bytes: Cow::from(rgba_bytes),
-            };
-
-            clipboard.set_image(image_data).map_err(|e| e.to_string())?;
-            log::info!("✅ Copied image to clipboard ({}x{})", width, height);
-
-            if show_notification {
-                #[cfg(not(target_os = "linux"))]
-                let _ = app.notification()
-                    .builder()
-                    .title("已复制")
-                    .body("图片已复制到剪贴板")
-                    .show();
-            }
-        }
-        ContentType::File => {
-            let path = String::from_utf8_lossy(&item.content).to_string();
-            clipboard.set_text(path.clone()).map_err(|e| e.to_string())?;
-            log::info!("✅ Copied file path to clipboard: {}", path);
-
-            if show_notification {
-                #[cfg(not(target_os = "linux"))]
-                let _ = app.notification()
-                    .builder()
-                    .title("已复制")
-                    .body("文件路径已复制到剪贴板")
-                    .show();
-            }
-        }
-        ContentType::Html | ContentType::Rtf => {
-            let text = String::from_utf8_lossy(&item.content).to_string();
-            clipboard.set_text(text).map_err(|e| e.to_string())?;
-            log::info!("✅ Copied rich text to clipboard as plain text");
-
-            if show_notification {
-                #[cfg(not(target_os = "linux"))]
-                let _ = app.notification()
-                    .builder()
-                    .title("已复制")
-                    .body("富文本已复制到剪贴板")
-                    .show();
-            }
+    // Clear marker after 2 seconds
+    let last_copied_by_us = state.last_copied_by_us.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let mut last_copied = safe_lock(&last_copied_by_us);
+        *last_copied = None;
+        log::debug!("🧹 Cleared self-copy marker");
+    });
+
+    // Burn-after-paste: count this paste and drop the clip once its limit is used up
+    if item.pastes_remaining.is_some() {
+        let storage = state.storage.clone();
+        let clip_id_for_paste = clip_id.to_string();
+        let burned = tauri::async_runtime::spawn_blocking(move || {
+            let storage = safe_lock(&storage);
+            storage.record_paste(&clip_id_for_paste)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+        if burned {
+            crate::sweeper::notify_clips_expired(app, &[clip_id.to_string()]);
         }
     }
 
@@ -401,11 +958,17 @@ pub async fn update_settings(
     let old_tray_text_length = state.settings.get().tray_text_length;
     let old_autostart = state.settings.get().enable_autostart;
     let old_locale = state.settings.get().locale;
+    let old_tray_sort_mode = state.settings.get().tray_sort_mode;
+    let old_mask_sensitive = state.settings.get().mask_sensitive;
+    let old_selection_options = state.settings.get().selection_options();
     let new_shortcut = settings.global_shortcut.clone();
     let shortcut_changed = old_shortcut != new_shortcut;
     let tray_text_changed = old_tray_text_length != settings.tray_text_length;
     let autostart_changed = old_autostart != settings.enable_autostart;
     let locale_changed = old_locale != settings.locale;
+    let tray_sort_changed = old_tray_sort_mode != settings.tray_sort_mode;
+    let mask_changed = old_mask_sensitive != settings.mask_sensitive;
+    let selection_changed = old_selection_options != settings.selection_options();
 
     state.settings.set(settings.clone());
     state.settings.save(&app)?;
@@ -454,12 +1017,19 @@ pub async fn update_settings(
         log::info!("Hotkey successfully updated to '{}'", new_shortcut);
     }
 
-    // Rebuild tray menu if text length or locale changed
-    if tray_text_changed || locale_changed {
+    // Rebuild tray menu if text length, locale, sort order or masking changed
+    if tray_text_changed || locale_changed || tray_sort_changed || mask_changed {
         log::info!("Tray settings changed, rebuilding menu...");
         update_tray_menu(&app);
     }
 
+    // Start, stop or reconfigure watching the PRIMARY selection
+    if selection_changed {
+        if let Some(monitor) = safe_lock(&state.monitor).as_mut() {
+            monitor.restart_selection();
+        }
+    }
+
     Ok(())
 }
 
@@ -561,19 +1131,26 @@ pub async fn migrate_data_location(
     new_path: String,
     delete_old: bool,
 ) -> Result<(), String> {
-    use crate::clipboard::ClipboardMonitor;
-    
     log::info!("Starting data migration to: {}, delete_old: {}", new_path, delete_old);
-    
-    // Stop clipboard monitoring during migration
-    {
-        let mut monitor_guard = state.monitor.lock().unwrap();
-        if let Some(monitor) = monitor_guard.take() {
-            drop(monitor);
-            log::info!("Clipboard monitoring stopped for migration");
-        }
+
+    // Stop clipboard monitoring during migration; this waits for the monitoring threads to end
+    if let Some(monitor) = safe_lock(&state.monitor).as_mut() {
+        monitor.stop();
+        log::info!("Clipboard monitoring stopped for migration");
     }
-    
+
+    let result = migrate_data(&app, &state, &new_path, delete_old);
+
+    // Restart clipboard monitoring, whether or not the migration worked
+    if let Some(monitor) = safe_lock(&state.monitor).as_mut() {
+        monitor.start();
+        log::info!("Clipboard monitoring restarted after migration");
+    }
+
+    result
+}
+
+fn migrate_data(app: &AppHandle, state: &AppState, new_path: &str, delete_old: bool) -> Result<(), String> {
     // Get current and new paths
     let default_path = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
@@ -581,26 +1158,19 @@ pub async fn migrate_data_location(
     let settings = state.settings.get();
     let custom_path = settings.custom_data_path.clone();
     let old_path = migration::get_data_directory(default_path.clone(), custom_path);
-    let new_path_buf = std::path::PathBuf::from(&new_path);
+    let new_path_buf = std::path::PathBuf::from(new_path);
     
     // Perform migration
     migration::migrate_data(&old_path, &new_path_buf, delete_old)?;
     
     // Update settings with new path
     let mut new_settings = settings.clone();
-    new_settings.custom_data_path = Some(new_path.clone());
+    new_settings.custom_data_path = Some(new_path.to_string());
     
     state.settings.set(new_settings.clone());
-    state.settings.save(&app)
+    state.settings.save(app)
         .map_err(|e| format!("Failed to save settings: {}", e))?;
     
     log::info!("Data migration completed successfully");
-    
-    // Restart clipboard monitoring
-    let monitor = ClipboardMonitor::new(app.clone(), state.last_copied_by_us.clone());
-    monitor.start();
-    *state.monitor.lock().unwrap() = Some(monitor);
-    log::info!("Clipboard monitoring restarted after migration");
-    
     Ok(())
 }
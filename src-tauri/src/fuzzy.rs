@@ -0,0 +1,287 @@
+// Fuzzy matching for search
+//
+// Patterns match as a substring when possible, otherwise as a subsequence scored
+// like fzf's v1 algorithm (bonuses for word starts and consecutive characters,
+// penalties for gaps). Words of 4+ characters also tolerate a small typo.
+use serde::Serialize;
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+/// Subsequence matches scoring below this (per pattern char) are too scattered to be useful
+const MIN_SCORE_PER_CHAR: i64 = SCORE_MATCH / 2;
+/// Only the first occurrences of a substring are compared for the best score
+const MAX_SUBSTRING_CANDIDATES: usize = 64;
+
+const PIN_BONUS: i64 = 24;
+/// (max age in days, bonus) for how recently a clip was captured
+const RECENCY_BONUSES: [(i64, i64); 3] = [(1, 24), (7, 16), (30, 8)];
+
+/// A highlighted range of characters, `start..end` in char indices
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of the matched characters, ascending
+    pub positions: Vec<usize>,
+}
+
+/// Case folding used on both sides of a match. Maps one char to one char so
+/// indices in the folded text stay valid in the original.
+pub fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn bonus_at(text: &[char], i: usize) -> i64 {
+    let current = text[i];
+    let Some(&prev) = i.checked_sub(1).and_then(|p| text.get(p)) else {
+        return BONUS_BOUNDARY;
+    };
+
+    if !prev.is_alphanumeric() && current.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if (prev.is_lowercase() && current.is_uppercase()) || (!prev.is_numeric() && current.is_numeric()) {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+/// Score matched positions in `text`
+pub fn score_positions(text: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+
+    for &pos in positions {
+        score += SCORE_MATCH + bonus_at(text, pos);
+        match prev {
+            Some(p) if pos == p + 1 => score += BONUS_CONSECUTIVE,
+            Some(p) => score -= PENALTY_GAP_START + PENALTY_GAP_EXTENSION * (pos - p - 2) as i64,
+            None => {}
+        }
+        prev = Some(pos);
+    }
+
+    score
+}
+
+fn substring_match(pattern: &[char], text: &[char]) -> Option<FuzzyMatch> {
+    let first = *pattern.first()?;
+
+    (0..text.len().saturating_sub(pattern.len() - 1))
+        .filter(|&start| fold(text[start]) == first)
+        .filter(|&start| pattern.iter().enumerate().all(|(i, &c)| fold(text[start + i]) == c))
+        .take(MAX_SUBSTRING_CANDIDATES)
+        .map(|start| {
+            let positions: Vec<usize> = (start..start + pattern.len()).collect();
+            FuzzyMatch { score: score_positions(text, &positions), positions }
+        })
+        .max_by_key(|m| (m.score, std::cmp::Reverse(m.positions[0])))
+}
+
+/// fzf v1: find the first subsequence match scanning forward, then scan backward from
+/// its end to shrink it to the shortest window
+fn subsequence_match(pattern: &[char], text: &[char]) -> Option<FuzzyMatch> {
+    let mut pi = 0;
+    let mut end = None;
+    for (i, &c) in text.iter().enumerate() {
+        if fold(c) == pattern[pi] {
+            pi += 1;
+            if pi == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+
+    let end = end?;
+    let mut positions = vec![0; pattern.len()];
+    let mut pi = pattern.len();
+    for i in (0..=end).rev() {
+        if fold(text[i]) == pattern[pi - 1] {
+            pi -= 1;
+            positions[pi] = i;
+            if pi == 0 {
+                break;
+            }
+        }
+    }
+
+    let score = score_positions(text, &positions);
+    (score >= MIN_SCORE_PER_CHAR * pattern.len() as i64).then_some(FuzzyMatch { score, positions })
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions)
+pub fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+fn max_typos(pattern_len: usize) -> usize {
+    match pattern_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Match the pattern against whole words of the text allowing a few typos
+fn typo_match(pattern: &[char], text: &[char]) -> Option<FuzzyMatch> {
+    let max = max_typos(pattern.len());
+    if max == 0 {
+        return None;
+    }
+
+    let mut best: Option<(usize, usize, usize)> = None;
+    let mut i = 0;
+    while i < text.len() {
+        if !text[i].is_alphanumeric() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < text.len() && text[i].is_alphanumeric() {
+            i += 1;
+        }
+
+        if (i - start).abs_diff(pattern.len()) > max {
+            continue;
+        }
+
+        let word: Vec<char> = text[start..i].iter().map(|&c| fold(c)).collect();
+        let distance = edit_distance(&word, pattern);
+        if distance <= max && best.is_none_or(|(d, _, _)| distance < d) {
+            best = Some((distance, start, i));
+        }
+    }
+
+    best.map(|(distance, start, end)| FuzzyMatch {
+        score: SCORE_MATCH * pattern.len() as i64 / 2 - SCORE_MATCH / 2 * distance as i64,
+        positions: (start..end).collect(),
+    })
+}
+
+/// Match a folded pattern against text. Substring matches are preferred, then
+/// subsequences (abbreviations), then words with a typo.
+pub fn fuzzy_match(pattern: &[char], text: &[char]) -> Option<FuzzyMatch> {
+    if pattern.is_empty() {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+
+    substring_match(pattern, text)
+        .or_else(|| subsequence_match(pattern, text))
+        .or_else(|| typo_match(pattern, text))
+}
+
+/// Final ranking score: match quality plus bonuses for pinned and recent clips
+pub fn rank_score(match_score: i64, is_pinned: bool, timestamp: i64, now: i64) -> i64 {
+    let age_days = (now - timestamp).max(0) / 86400;
+    let recency = RECENCY_BONUSES
+        .iter()
+        .find(|(max_days, _)| age_days < *max_days)
+        .map(|(_, bonus)| *bonus)
+        .unwrap_or(0);
+
+    match_score + recency + if is_pinned { PIN_BONUS } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    fn matched(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+        fuzzy_match(&chars(&pattern.to_lowercase()), &chars(text))
+    }
+
+    #[test]
+    fn test_substring_prefers_word_start() {
+        let m = matched("pod", "kubectl get pods | grep pod-7").unwrap();
+        assert_eq!(m.positions, vec![12, 13, 14]);
+        assert!(matched("GET", "kubectl get pods").is_some());
+    }
+
+    #[test]
+    fn test_abbreviations() {
+        let m = matched("kgp", "kubectl get pods").unwrap();
+        assert_eq!(m.positions, vec![0, 8, 12]);
+        assert!(matched("gc", "git commit --amend").is_some());
+        // camelCase humps
+        assert_eq!(matched("gbi", "getUserById").map(|m| m.positions), Some(vec![0, 7, 9]));
+    }
+
+    #[test]
+    fn test_scattered_matches_are_rejected() {
+        let prose = "the box was heavy and the sky was hazy";
+        assert!(matched("xyz", prose).is_none());
+        assert!(matched("kgq", "kubectl get pods").is_none());
+    }
+
+    #[test]
+    fn test_typos() {
+        assert_eq!(edit_distance(&chars("kubectl"), &chars("kubetcl")), 1);
+        assert_eq!(edit_distance(&chars("docker"), &chars("dokcer")), 1);
+        assert_eq!(edit_distance(&chars("abc"), &chars("xyz")), 3);
+
+        let m = matched("kubetcl", "run kubectl apply").unwrap();
+        assert_eq!(m.positions, (4..11).collect::<Vec<_>>());
+        assert!(matched("postgers", "postgres://localhost").is_some());
+        // Short words must match exactly
+        assert!(matched("lss", "ls -la").is_none());
+        assert!(matched("dcoker", "podman").is_none());
+    }
+
+    #[test]
+    fn test_match_quality_ordering() {
+        let score = |pattern: &str, text: &str| matched(pattern, text).unwrap().score;
+        let exact = score("deploy", "deploy staging");
+        let inner = score("deploy", "redeploying staging");
+        let abbrev = score("dpl", "deploy staging");
+        let typo = score("delpoy", "deploy staging");
+
+        assert!(exact > inner, "{} > {}", exact, inner);
+        assert!(inner > abbrev, "{} > {}", inner, abbrev);
+        assert!(abbrev > typo, "{} > {}", abbrev, typo);
+    }
+
+    #[test]
+    fn test_rank_score() {
+        let now = 100 * 86400;
+        assert_eq!(rank_score(50, false, now - 3600, now), 74);
+        assert_eq!(rank_score(50, false, now - 3 * 86400, now), 66);
+        assert_eq!(rank_score(50, true, now - 90 * 86400, now), 74);
+        assert_eq!(rank_score(50, false, now - 90 * 86400, now), 50);
+    }
+}
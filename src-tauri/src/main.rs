@@ -8,7 +8,26 @@ mod settings;
 mod migration;
 mod tray;
 mod commands;
-
+mod autoclear;
+mod capture;
+mod capture_rules;
+mod classify;
+mod monitor;
+mod sweeper;
+mod fuzzy;
+mod normalize;
+mod query;
+mod richtext;
+mod selection;
+mod sensitive;
+mod source_app;
+mod transform;
+#[cfg(target_os = "linux")]
+mod x11_clipboard;
+#[cfg(target_os = "windows")]
+mod windows_clipboard;
+
+use autoclear::ClipboardRestorer;
 use clipboard::ClipboardMonitor;
 use storage::ClipStorage;
 use crypto::Crypto;
@@ -51,6 +70,8 @@ pub struct AppState {
     pub crypto: Arc<Crypto>,
     pub settings: Arc<SettingsManager>,
     pub last_copied_by_us: Arc<Mutex<Option<String>>>,
+    /// What pastes of auto-cleared clips replaced on the clipboard
+    pub clipboard_restorer: ClipboardRestorer,
     pub icon_cache: Arc<TrayIconCache>,
 }
 
@@ -169,6 +190,13 @@ fn main() {
                 Some(crypto.clone())
             ).expect("Failed to initialize database");
 
+            // Clips from before text was classified get their labels once
+            match storage.classify_unlabelled() {
+                Ok(0) => {}
+                Ok(count) => log::info!("🏷️ Labelled {} text clips", count),
+                Err(e) => log::warn!("Failed to label text clips: {}", e),
+            }
+
             let last_copied_by_us = Arc::new(Mutex::new(None));
             let icon_cache = Arc::new(TrayIconCache::new());
 
@@ -178,6 +206,7 @@ fn main() {
                 crypto: crypto.clone(),
                 settings: settings_manager.clone(),
                 last_copied_by_us: last_copied_by_us.clone(),
+                clipboard_restorer: ClipboardRestorer::default(),
                 icon_cache: icon_cache.clone(),
             };
 
@@ -263,12 +292,14 @@ fn main() {
             let app_handle = app.handle().clone();
             let state: tauri::State<AppState> = app_handle.state();
 
-            let monitor = ClipboardMonitor::new(app_handle.clone(), last_copied_by_us.clone());
+            let mut monitor = ClipboardMonitor::new(app_handle.clone(), state.storage.clone(), last_copied_by_us.clone());
             monitor.start();
 
             *safe_lock(&state.monitor) = Some(monitor);
             log::info!("Clipboard monitoring started");
 
+            sweeper::start_expiry_sweeper(app_handle.clone());
+
             // Register global shortcuts
             let state: tauri::State<AppState> = app_handle.state();
             let current_shortcut = state.settings.get().global_shortcut;
@@ -296,10 +327,35 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             get_clipboard_history,
             search_clips,
+            get_saved_searches,
+            save_search,
+            delete_saved_search,
+            run_saved_search,
             toggle_pin,
+            move_pin,
+            get_pin_groups,
+            create_pin_group,
+            rename_pin_group,
+            delete_pin_group,
+            assign_pin_group,
+            update_clip_content,
+            set_clip_tags,
+            get_all_tags,
+            update_clip_details,
+            set_clip_expiry,
+            set_clip_sensitive,
+            set_clip_clear_after,
+            set_clip_paste_limit,
+            list_revisions,
+            revert_revision,
             delete_clip,
             get_settings,
             update_settings,
+            preview_transforms,
+            test_capture_rules,
+            pause_monitoring,
+            resume_monitoring,
+            monitoring_status,
             check_clipboard_permission,
             clear_all_history,
             clear_non_pinned_history,
@@ -0,0 +1,266 @@
+// Clipboard monitor lifecycle: a capture thread that can be paused, resumed, stopped and restarted
+//
+// The thread waits for clipboard changes and runs each through the capture pipeline.
+// Stopping sets a flag the thread checks at least once per poll interval and then joins
+// it, so a restarted monitor never runs next to the old one. The pipeline outlives the
+// thread: a restart remembers the clipboard state it last saw instead of capturing it again.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::capture::{CaptureEvents, CaptureOptions, CapturePipeline, ClipboardBackend};
+
+/// Whether clips are being captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MonitorStatus {
+    Running,
+    /// Watching, but what is copied isn't captured (not even after resuming)
+    Paused,
+    Stopped,
+}
+
+pub struct CaptureMonitor<E> {
+    pipeline: Arc<Mutex<CapturePipeline<E>>>,
+    paused: Arc<AtomicBool>,
+    /// Stop flag of the running thread; every run gets a fresh one
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<E: CaptureEvents + Send + 'static> CaptureMonitor<E> {
+    /// A stopped monitor
+    pub fn new(pipeline: CapturePipeline<E>) -> Self {
+        Self {
+            pipeline: Arc::new(Mutex::new(pipeline)),
+            paused: Arc::new(AtomicBool::new(false)),
+            stop: Arc::new(AtomicBool::new(true)),
+            thread: None,
+        }
+    }
+
+    /// Start capturing on a new thread, unless already running. `connect` opens the
+    /// clipboard on that thread; `options` is read before every capture so setting
+    /// changes apply right away.
+    pub fn start<B, C, O>(&mut self, connect: C, options: O, poll_interval: Duration)
+    where
+        B: ClipboardBackend,
+        C: FnOnce() -> Result<B, String> + Send + 'static,
+        O: Fn() -> CaptureOptions + Send + 'static,
+    {
+        if self.status() != MonitorStatus::Stopped {
+            return;
+        }
+        // Reap a thread that ended on its own (the clipboard couldn't be opened)
+        self.stop();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let paused = self.paused.clone();
+        let pipeline = self.pipeline.clone();
+        let thread_stop = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut clipboard = match connect() {
+                Ok(clipboard) => clipboard,
+                Err(e) => {
+                    log::error!("Failed to open the clipboard for monitoring: {}", e);
+                    return;
+                }
+            };
+            log::info!("Clipboard monitoring thread started");
+
+            while !thread_stop.load(Ordering::SeqCst) {
+                if !clipboard.wait_for_change(poll_interval) || thread_stop.load(Ordering::SeqCst) {
+                    continue;
+                }
+
+                let mut pipeline = pipeline.lock().unwrap_or_else(|poisoned| {
+                    log::warn!("⚠️ Recovered from poisoned lock in clipboard monitor");
+                    poisoned.into_inner()
+                });
+                if paused.load(Ordering::SeqCst) {
+                    pipeline.skip(&mut clipboard);
+                } else {
+                    pipeline.process(&mut clipboard, &options());
+                }
+            }
+
+            log::info!("Clipboard monitoring thread stopped");
+        });
+
+        self.stop = stop;
+        self.thread = Some(thread);
+    }
+
+    /// Stop the capture thread and wait for it to finish
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::error!("Clipboard monitoring thread panicked");
+            }
+        }
+    }
+
+    /// Change the clipboard with `write` without the change being captured. Captures
+    /// wait meanwhile, then see the clipboard as already handled.
+    pub fn without_capture<B: ClipboardBackend, T>(&self, clipboard: &mut B, write: impl FnOnce(&mut B) -> T) -> T {
+        write_uncaptured(&self.pipeline, clipboard, write)
+    }
+
+    /// The pipeline, for changing the clipboard from another thread with `write_uncaptured`
+    pub fn pipeline(&self) -> Arc<Mutex<CapturePipeline<E>>> {
+        self.pipeline.clone()
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> MonitorStatus {
+        match &self.thread {
+            Some(thread) if !thread.is_finished() => {
+                if self.paused.load(Ordering::SeqCst) {
+                    MonitorStatus::Paused
+                } else {
+                    MonitorStatus::Running
+                }
+            }
+            _ => MonitorStatus::Stopped,
+        }
+    }
+}
+
+/// Change the clipboard with `write` while holding `pipeline`, which then sees the
+/// change as already handled
+pub fn write_uncaptured<E: CaptureEvents, B: ClipboardBackend, T>(
+    pipeline: &Mutex<CapturePipeline<E>>,
+    clipboard: &mut B,
+    write: impl FnOnce(&mut B) -> T,
+) -> T {
+    let mut pipeline = pipeline.lock().unwrap_or_else(|poisoned| {
+        log::warn!("⚠️ Recovered from poisoned lock in clipboard monitor");
+        poisoned.into_inner()
+    });
+    let result = write(clipboard);
+    pipeline.skip(clipboard);
+    result
+}
+
+impl<E> Drop for CaptureMonitor<E> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_encoding::BASE64;
+    use std::time::Instant;
+
+    use crate::capture::testing::{MemoryClipboard, RecordedEvents};
+    use crate::storage::ClipStorage;
+
+    const POLL: Duration = Duration::from_millis(5);
+
+    struct Fixture {
+        clipboard: Arc<Mutex<MemoryClipboard>>,
+        events: RecordedEvents,
+        monitor: CaptureMonitor<RecordedEvents>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let storage = Arc::new(Mutex::new(ClipStorage::new(":memory:", None).unwrap()));
+            let events = RecordedEvents::default();
+            let pipeline = CapturePipeline::new(storage, Arc::new(Mutex::new(None)), events.clone());
+            Self { clipboard: Arc::default(), events, monitor: CaptureMonitor::new(pipeline) }
+        }
+
+        fn start(&mut self) {
+            let clipboard = self.clipboard.clone();
+            let options = || CaptureOptions { max_history_items: 100, ..Default::default() };
+            self.monitor.start(move || Ok(clipboard), options, POLL);
+        }
+
+        fn copy(&self, text: &str) {
+            self.clipboard.lock().unwrap().copy(MemoryClipboard { text: Some(text.to_string()), ..Default::default() });
+        }
+
+        /// Texts of the clips reported so far, once the monitor had time to catch up
+        fn captured(&self, expected: usize) -> Vec<String> {
+            let deadline = Instant::now() + Duration::from_secs(1);
+            while self.events.0.lock().unwrap().len() < expected && Instant::now() < deadline {
+                std::thread::sleep(POLL);
+            }
+            // Give a duplicate capture the chance to show up
+            std::thread::sleep(POLL * 10);
+            let events = self.events.0.lock().unwrap();
+            events.iter().map(|item| String::from_utf8(BASE64.decode(item.content.as_bytes()).unwrap()).unwrap()).collect()
+        }
+    }
+
+    #[test]
+    fn test_restart_never_duplicates_captures() {
+        let mut fixture = Fixture::new();
+        fixture.start();
+        fixture.start();
+        assert_eq!(fixture.monitor.status(), MonitorStatus::Running);
+
+        fixture.copy("one");
+        assert_eq!(fixture.captured(1), vec!["one"]);
+
+        // A restart neither captures the unchanged clipboard again nor leaves the old thread running
+        fixture.monitor.stop();
+        assert_eq!(fixture.monitor.status(), MonitorStatus::Stopped);
+        fixture.start();
+        fixture.copy("two");
+        assert_eq!(fixture.captured(2), vec!["one", "two"]);
+
+        // Nothing is captured once stopped
+        fixture.monitor.stop();
+        fixture.copy("three");
+        assert_eq!(fixture.captured(3), vec!["one", "two"]);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let mut fixture = Fixture::new();
+        fixture.start();
+        fixture.monitor.pause();
+        assert_eq!(fixture.monitor.status(), MonitorStatus::Paused);
+
+        // What is copied while paused stays uncaptured after resuming
+        fixture.copy("secret");
+        assert!(fixture.captured(1).is_empty());
+        fixture.monitor.resume();
+        assert!(fixture.captured(1).is_empty());
+
+        fixture.copy("public");
+        assert_eq!(fixture.captured(1), vec!["public"]);
+
+        // Changes made without capture aren't picked up either
+        let mut clipboard = fixture.clipboard.clone();
+        fixture.monitor.without_capture(&mut clipboard, |clipboard| {
+            clipboard.lock().unwrap().copy(MemoryClipboard { text: Some("restored".to_string()), ..Default::default() })
+        });
+        assert_eq!(fixture.captured(2), vec!["public"]);
+
+        // A clipboard that can't be opened leaves the monitor stopped
+        let mut broken = Fixture::new();
+        broken.monitor.start(|| Err::<MemoryClipboard, _>("no display".to_string()), CaptureOptions::default, POLL);
+        broken.monitor.stop();
+        assert_eq!(broken.monitor.status(), MonitorStatus::Stopped);
+    }
+}
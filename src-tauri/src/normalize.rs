@@ -0,0 +1,174 @@
+// Text normalization for search
+//
+// Clip text is NFKC-normalized before matching, which folds full-width and
+// half-width forms (`ＡＢＣ` → `ABC`, `ｶﾀｶﾅ` → `カタカナ`) and compatibility
+// characters. Chinese text additionally gets pinyin renderings so `fuwuqi` or
+// `fwq` find 服务器. Every normalized char remembers which original chars it came
+// from, so match positions can be highlighted in the original text.
+use pinyin::ToPinyin;
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::fuzzy::{fold, MatchRange};
+
+/// Normalized text with a map back to the original
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedText {
+    /// Normalized chars, case preserved (fuzzy scoring uses case for camelCase bonuses)
+    pub chars: Vec<char>,
+    /// `chars` case-folded, one char per char
+    pub folded: String,
+    /// For each normalized char, the range of original char indices it came from
+    spans: Vec<(usize, usize)>,
+}
+
+impl NormalizedText {
+    fn from_parts(chars: Vec<char>, spans: Vec<(usize, usize)>) -> Self {
+        let folded = chars.iter().map(|&c| fold(c)).collect();
+        Self { chars, folded, spans }
+    }
+
+    /// Char index in `chars` of a byte offset in `folded`
+    pub fn char_index(&self, byte: usize) -> usize {
+        self.folded[..byte].chars().count()
+    }
+
+    /// Map matched positions in `chars` to merged ranges of the original text
+    pub fn original_ranges(&self, positions: &[usize]) -> Vec<MatchRange> {
+        let mut ranges: Vec<MatchRange> = Vec::new();
+        for &(start, end) in positions.iter().filter_map(|&pos| self.spans.get(pos)) {
+            match ranges.last_mut() {
+                Some(range) if start <= range.end => range.end = range.end.max(end),
+                _ => ranges.push(MatchRange { start, end }),
+            }
+        }
+        ranges
+    }
+}
+
+/// NFKC-normalize text one cluster (base char plus combining marks) at a time,
+/// so each output char can be traced back to its cluster
+pub fn normalize(text: &str) -> NormalizedText {
+    let original: Vec<char> = text.chars().collect();
+    let mut chars = Vec::with_capacity(original.len());
+    let mut spans = Vec::with_capacity(original.len());
+
+    let mut i = 0;
+    while i < original.len() {
+        let start = i;
+        i += 1;
+        while i < original.len() && canonical_combining_class(original[i]) != 0 {
+            i += 1;
+        }
+
+        // ASCII is already normalized, skip the allocation for the common case
+        if i - start == 1 && original[start].is_ascii() {
+            chars.push(original[start]);
+            spans.push((start, i));
+            continue;
+        }
+
+        for c in original[start..i].iter().collect::<String>().nfkc() {
+            chars.push(c);
+            spans.push((start, i));
+        }
+    }
+
+    NormalizedText::from_parts(chars, spans)
+}
+
+/// Normalize a search pattern the same way as the text, then case-fold it
+pub fn normalize_pattern(pattern: &str) -> String {
+    pattern.nfkc().map(fold).collect()
+}
+
+/// Pinyin renderings of normalized text: full syllables (`FuWuQi`) and initials
+/// (`FWQ`). Syllables are capitalized so fuzzy matching sees them as word starts.
+/// `None` if the text has no Chinese characters.
+pub fn pinyin_variants(text: &NormalizedText) -> Option<[NormalizedText; 2]> {
+    if !text.chars.iter().any(|c| c.to_pinyin().is_some()) {
+        return None;
+    }
+
+    let (mut full, mut full_spans) = (Vec::new(), Vec::new());
+    let (mut initials, mut initial_spans) = (Vec::new(), Vec::new());
+
+    for (&c, &span) in text.chars.iter().zip(&text.spans) {
+        match c.to_pinyin() {
+            Some(pinyin) => {
+                for (i, letter) in pinyin.plain().chars().enumerate() {
+                    full.push(if i == 0 { letter.to_ascii_uppercase() } else { letter });
+                    full_spans.push(span);
+                }
+                for letter in pinyin.first_letter().chars() {
+                    initials.push(letter.to_ascii_uppercase());
+                    initial_spans.push(span);
+                }
+            }
+            None => {
+                full.push(c);
+                full_spans.push(span);
+                initials.push(c);
+                initial_spans.push(span);
+            }
+        }
+    }
+
+    Some([
+        NormalizedText::from_parts(full, full_spans),
+        NormalizedText::from_parts(initials, initial_spans),
+    ])
+}
+
+/// All renderings of a text that search terms are matched against
+pub fn search_variants(text: &str) -> Vec<NormalizedText> {
+    let normalized = normalize(text);
+    let pinyin = pinyin_variants(&normalized);
+
+    let mut variants = vec![normalized];
+    variants.extend(pinyin.into_iter().flatten());
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &NormalizedText) -> String {
+        text.chars.iter().collect()
+    }
+
+    #[test]
+    fn test_width_folding() {
+        assert_eq!(string(&normalize("ＡＢＣ　１２３")), "ABC 123");
+        assert_eq!(normalize_pattern("ＡＢＣ"), "abc");
+        assert_eq!(string(&normalize("ｶﾀｶﾅ")), "カタカナ");
+        assert_eq!(normalize_pattern("ﬁle"), "file");
+    }
+
+    #[test]
+    fn test_spans_map_back_to_original() {
+        // "e" + combining acute composes into one char covering two original chars
+        let text = normalize("cafe\u{301} ＯＫ");
+        assert_eq!(string(&text), "café OK");
+        assert_eq!(text.original_ranges(&[3]), vec![MatchRange { start: 3, end: 5 }]);
+        assert_eq!(text.original_ranges(&[5, 6]), vec![MatchRange { start: 6, end: 8 }]);
+
+        // One original char expanding to several normalized chars
+        let text = normalize("㎏x");
+        assert_eq!(string(&text), "kgx");
+        assert_eq!(text.original_ranges(&[1, 2]), vec![MatchRange { start: 0, end: 2 }]);
+    }
+
+    #[test]
+    fn test_pinyin_variants() {
+        let [full, initials] = pinyin_variants(&normalize("连接服务器 ok")).unwrap();
+        assert_eq!(string(&full), "LianJieFuWuQi ok");
+        assert_eq!(string(&initials), "LJFWQ ok");
+        assert_eq!(initials.original_ranges(&[2, 3, 4]), vec![MatchRange { start: 2, end: 5 }]);
+        assert_eq!(full.original_ranges(&[7]), vec![MatchRange { start: 2, end: 3 }]);
+
+        assert!(pinyin_variants(&normalize("no chinese here")).is_none());
+        assert_eq!(search_variants("plain").len(), 1);
+    }
+}
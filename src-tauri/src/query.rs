@@ -0,0 +1,1001 @@
+// Search query language
+//
+// Examples:
+//   kubectl type:text pinned:yes
+//   kind:url OR kind:email
+//   -selection:primary
+//   "exact phrase" -tag:deploy
+//   (type:image OR type:file) after:yesterday size:>1mb
+//   /^https?:\/\// before:2026-09-01
+//
+// Bare words match fuzzily (see fuzzy.rs) and phrases as exact substrings. Both
+// ignore case and width, and also match the pinyin of Chinese text (see
+// normalize.rs). Terms are combined with AND, `OR` (or `|`) has lower precedence,
+// `-` or `NOT` negates, parentheses group.
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+use regex::Regex;
+use rusqlite::types::Value;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::classify::TextKind;
+use crate::fuzzy::{fuzzy_match, score_positions, MatchRange};
+use crate::normalize::{normalize_pattern, search_variants, NormalizedText};
+use crate::richtext::{html_to_text, rtf_to_text};
+use crate::storage::{ClipItem, ContentType, Selection};
+
+/// A syntax error with the character position where it was found
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeCmp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+}
+
+#[derive(Debug, Clone)]
+pub enum Term {
+    /// Bare word, normalized and case-folded
+    Text(String),
+    /// Quoted phrase, normalized and case-folded
+    Phrase(String),
+    Regex(Regex),
+    Type(ContentType),
+    /// What the text holds, as labelled at capture (see classify.rs)
+    Kind(TextKind),
+    /// Captured from the clipboard or the PRIMARY selection
+    Selection(Selection),
+    Pinned(bool),
+    Tag(String),
+    /// Captured before this Unix timestamp
+    Before(i64),
+    /// Captured at or after this Unix timestamp
+    After(i64),
+    Size(SizeCmp, u64),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+/// A parsed search query. The empty query matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub expr: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Regex(String),
+    LParen,
+    RParen,
+    Minus,
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug)]
+struct Spanned {
+    token: Token,
+    position: usize,
+}
+
+fn error<T>(message: impl Into<String>, position: usize) -> Result<T, QueryError> {
+    Err(QueryError { message: message.into(), position })
+}
+
+/// Read a double-quoted string starting at `start` (the opening quote).
+/// Returns the unescaped content and the index after the closing quote.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QueryError> {
+    let mut value = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                value.push(chars[i + 1]);
+                i += 2;
+            }
+            '"' => return Ok((value, i + 1)),
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    error("Unterminated quote", start)
+}
+
+/// Try to read a `/regex/` starting at `start`. The closing slash must end the token,
+/// so paths like `/usr/bin` are left to be read as words.
+fn read_regex(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut pattern = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'/') => {
+                pattern.push('/');
+                i += 2;
+            }
+            '\\' if i + 1 < chars.len() => {
+                pattern.push('\\');
+                pattern.push(chars[i + 1]);
+                i += 2;
+            }
+            '/' => {
+                let ends_token = chars.get(i + 1).is_none_or(|c| c.is_whitespace() || *c == ')');
+                if ends_token && !pattern.is_empty() {
+                    return Some((pattern, i + 1));
+                }
+                pattern.push('/');
+                i += 1;
+            }
+            c => {
+                pattern.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    None
+}
+
+fn tokenize(input: &str) -> Result<Vec<Spanned>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let token = match c {
+            '(' => {
+                i += 1;
+                Token::LParen
+            }
+            ')' => {
+                i += 1;
+                Token::RParen
+            }
+            '|' => {
+                i += 1;
+                while chars.get(i) == Some(&'|') {
+                    i += 1;
+                }
+                Token::Or
+            }
+            '"' => {
+                let (value, next) = read_quoted(&chars, i)?;
+                i = next;
+                Token::Phrase(value)
+            }
+            '-' if chars.get(i + 1).is_some_and(|c| !c.is_whitespace()) => {
+                i += 1;
+                Token::Minus
+            }
+            _ => {
+                if c == '/' {
+                    if let Some((pattern, next)) = read_regex(&chars, i) {
+                        tokens.push(Spanned { token: Token::Regex(pattern), position });
+                        i = next;
+                        continue;
+                    }
+                }
+
+                let mut word = String::new();
+                while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+                    // field:"quoted value"
+                    if chars[i] == '"' && word.ends_with(':') {
+                        let (value, next) = read_quoted(&chars, i)?;
+                        word.push_str(&value);
+                        i = next;
+                        continue;
+                    }
+                    word.push(chars[i]);
+                    i += 1;
+                }
+
+                match word.as_str() {
+                    "AND" | "&&" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                }
+            }
+        };
+
+        tokens.push(Spanned { token, position });
+    }
+
+    Ok(tokens)
+}
+
+fn start_of_day(date: NaiveDate) -> Option<i64> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    Local.from_local_datetime(&midnight).earliest().map(|dt| dt.timestamp())
+}
+
+/// Parse a date for `before:`/`after:`: `YYYY-MM-DD`, `today`, `yesterday`,
+/// or a relative age such as `12h`, `3d`, `2w`. Negative ages and ones reaching
+/// past the dates chrono can represent are invalid.
+fn parse_date(value: &str, now: DateTime<Local>) -> Option<i64> {
+    match value.to_lowercase().as_str() {
+        "today" => start_of_day(now.date_naive()),
+        "yesterday" => start_of_day(now.date_naive() - Duration::days(1)),
+        value => {
+            if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                return start_of_day(date);
+            }
+
+            let unit = value.chars().last()?;
+            let amount: i64 = value[..value.len() - unit.len_utf8()].parse().ok().filter(|amount| *amount >= 0)?;
+            let age = match unit {
+                'h' => Duration::try_hours(amount)?,
+                'd' => Duration::try_days(amount)?,
+                'w' => Duration::try_weeks(amount)?,
+                _ => return None,
+            };
+            now.checked_sub_signed(age).map(|date| date.timestamp())
+        }
+    }
+}
+
+/// Parse `size:` values such as `>1mb`, `<=512kb`, `=0`, `2048`
+fn parse_size(value: &str) -> Option<(SizeCmp, u64)> {
+    let (cmp, rest) = [
+        (">=", SizeCmp::Ge),
+        ("<=", SizeCmp::Le),
+        (">", SizeCmp::Gt),
+        ("<", SizeCmp::Lt),
+        ("=", SizeCmp::Eq),
+    ]
+    .iter()
+    .find_map(|(prefix, cmp)| value.strip_prefix(prefix).map(|rest| (*cmp, rest)))
+    .unwrap_or((SizeCmp::Ge, value));
+
+    let rest = rest.to_lowercase();
+    let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+    let (number, unit) = rest.split_at(number_end);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier = match unit {
+        "" | "b" => 1u64,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    Some((cmp, (number * multiplier as f64) as u64))
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+    input_len: usize,
+    now: DateTime<Local>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map(|t| t.position).unwrap_or(self.input_len)
+    }
+
+    fn expect_operand(&self, after: &str) -> Result<(), QueryError> {
+        match self.peek() {
+            None | Some(Token::RParen) | Some(Token::Or) | Some(Token::And) => {
+                error(format!("Expected a search term after {}", after), self.position())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = vec![self.parse_and()?];
+
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            self.expect_operand("OR")?;
+            terms.push(self.parse_and()?);
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Or(terms) })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = vec![self.parse_unary()?];
+
+        loop {
+            match self.peek() {
+                None | Some(Token::RParen) | Some(Token::Or) => break,
+                Some(Token::And) => {
+                    self.pos += 1;
+                    self.expect_operand("AND")?;
+                }
+                _ => terms.push(self.parse_unary()?),
+            }
+        }
+
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::And(terms) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        let position = self.position();
+        let Some(spanned) = self.tokens.get(self.pos) else {
+            return error("Expected a search term", position);
+        };
+        let token = spanned.token.clone();
+        self.pos += 1;
+
+        match token {
+            Token::Minus | Token::Not => {
+                self.expect_operand(if token == Token::Minus { "'-'" } else { "NOT" })?;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Token::LParen => {
+                if self.peek() == Some(&Token::RParen) {
+                    return error("Empty parentheses", position);
+                }
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return error("Missing closing parenthesis", position);
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Token::RParen => error("Unexpected ')'", position),
+            Token::And | Token::Or => error("Expected a search term before operator", position),
+            Token::Phrase(phrase) => Ok(Expr::Term(Term::Phrase(normalize_pattern(&phrase)))),
+            Token::Regex(pattern) => match Regex::new(&pattern) {
+                Ok(regex) => Ok(Expr::Term(Term::Regex(regex))),
+                Err(e) => error(format!("Invalid regular expression: {}", e), position),
+            },
+            Token::Word(word) => self.parse_word(&word, position).map(Expr::Term),
+        }
+    }
+
+    fn parse_word(&self, word: &str, position: usize) -> Result<Term, QueryError> {
+        let Some((field, value)) = word.split_once(':') else {
+            return Ok(Term::Text(normalize_pattern(word)));
+        };
+
+        let field = field.to_lowercase();
+        let value_position = position + field.chars().count() + 1;
+        let known_field = matches!(field.as_str(), "type" | "kind" | "selection" | "pinned" | "tag" | "before" | "after" | "size");
+
+        if known_field && value.is_empty() {
+            return error(format!("Missing value for '{}:'", field), value_position);
+        }
+
+        match field.as_str() {
+            "type" => ContentType::from_name(&value.to_lowercase())
+                .map(Term::Type)
+                .ok_or(())
+                .or_else(|_| error(format!("Unknown type '{}'", value), value_position)),
+            "kind" => TextKind::from_name(&value.to_lowercase())
+                .map(Term::Kind)
+                .ok_or(())
+                .or_else(|_| error(format!("Unknown kind '{}'", value), value_position)),
+            "selection" => Selection::from_name(&value.to_lowercase())
+                .map(Term::Selection)
+                .ok_or(())
+                .or_else(|_| error(format!("Expected clipboard or primary for 'selection:', got '{}'", value), value_position)),
+            "pinned" => match value.to_lowercase().as_str() {
+                "yes" | "true" | "1" => Ok(Term::Pinned(true)),
+                "no" | "false" | "0" => Ok(Term::Pinned(false)),
+                _ => error(format!("Expected yes or no for 'pinned:', got '{}'", value), value_position),
+            },
+            "tag" => Ok(Term::Tag(value.to_lowercase())),
+            "before" | "after" => match parse_date(value, self.now) {
+                Some(ts) if field == "before" => Ok(Term::Before(ts)),
+                Some(ts) => Ok(Term::After(ts)),
+                None => error(format!("Invalid date '{}'", value), value_position),
+            },
+            "size" => parse_size(value)
+                .map(|(cmp, bytes)| Term::Size(cmp, bytes))
+                .ok_or(())
+                .or_else(|_| error(format!("Invalid size '{}'", value), value_position)),
+            // Not a filter (e.g. "https://..."), search for the whole word
+            _ => Ok(Term::Text(normalize_pattern(word))),
+        }
+    }
+}
+
+/// Which part of a clip a search term matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchField {
+    /// Text content; the plain-text rendering for HTML and RTF clips
+    Content,
+    /// File names of a file clip, one per line
+    FileName,
+    /// Full paths of a file clip, one per line
+    Path,
+    Title,
+    Note,
+}
+
+/// A matched range of characters, `start..end` in char indices of the field's text
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMatch {
+    pub field: SearchField,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A searchable text field of an item
+struct Field {
+    kind: SearchField,
+    text: String,
+    /// Normalized renderings of `text` (see normalize.rs)
+    variants: Vec<NormalizedText>,
+}
+
+impl Field {
+    fn new(kind: SearchField, text: String) -> Self {
+        Self {
+            kind,
+            variants: search_variants(&text),
+            text,
+        }
+    }
+
+    /// Char index of a byte offset in `text`
+    fn char_index(&self, byte: usize) -> usize {
+        self.text[..byte].chars().count()
+    }
+
+    /// Best match of `match_variant` over the renderings, with positions mapped to the original.
+    /// Ties go to the earlier rendering.
+    fn best_variant(&self, match_variant: impl Fn(&NormalizedText) -> Option<(i64, Vec<usize>)>) -> Option<(i64, Vec<MatchRange>)> {
+        self.variants
+            .iter()
+            .rev()
+            .filter_map(|variant| {
+                let (score, positions) = match_variant(variant)?;
+                Some((score, variant.original_ranges(&positions)))
+            })
+            .max_by_key(|(score, _)| *score)
+    }
+}
+
+/// Text of an item that free-text terms are matched against
+struct Haystack {
+    fields: Vec<Field>,
+}
+
+impl Haystack {
+    fn new(item: &ClipItem) -> Self {
+        let mut fields = Vec::new();
+        let content = std::str::from_utf8(&item.content).ok();
+
+        match (&item.content_type, content) {
+            (ContentType::Text, Some(text)) => fields.push(Field::new(SearchField::Content, text.to_string())),
+            (ContentType::Html, Some(html)) => {
+                let text = item.plain_text.clone().unwrap_or_else(|| html_to_text(html));
+                fields.push(Field::new(SearchField::Content, text));
+            }
+            (ContentType::Rtf, Some(rtf)) => {
+                let text = item.plain_text.clone().unwrap_or_else(|| rtf_to_text(rtf));
+                fields.push(Field::new(SearchField::Content, text));
+            }
+            (ContentType::File, Some(paths)) => {
+                let names: Vec<String> = paths
+                    .lines()
+                    .filter_map(|path| Path::new(path.trim()).file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .collect();
+                fields.push(Field::new(SearchField::FileName, names.join("\n")));
+                fields.push(Field::new(SearchField::Path, paths.to_string()));
+            }
+            // Images are only found by their title and note
+            _ => {}
+        }
+
+        fields.extend(item.title.iter().map(|title| Field::new(SearchField::Title, title.clone())));
+        fields.extend(item.note.iter().map(|note| Field::new(SearchField::Note, note.clone())));
+
+        Self { fields }
+    }
+
+    /// Best-scoring match over all fields. Ties go to the earlier field.
+    fn best(&self, mut match_field: impl FnMut(&Field) -> Option<(i64, Vec<MatchRange>)>) -> Option<Hit> {
+        self.fields
+            .iter()
+            .rev()
+            .filter_map(|field| {
+                let (score, ranges) = match_field(field)?;
+                let matches = ranges
+                    .into_iter()
+                    .map(|range| FieldMatch { field: field.kind, start: range.start, end: range.end })
+                    .collect();
+                Some(Hit { score, matches })
+            })
+            .max_by_key(|hit| hit.score)
+    }
+}
+
+/// Result of evaluating a query against one item
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hit {
+    /// Sum of the match scores of the free-text terms (0 for filters only)
+    pub score: i64,
+    /// Matched ranges, sorted by field and position and non-overlapping
+    pub matches: Vec<FieldMatch>,
+}
+
+impl Hit {
+    fn merge(mut self, other: Hit) -> Hit {
+        self.score += other.score;
+        self.matches.extend(other.matches);
+        self
+    }
+
+    fn normalized(mut self) -> Hit {
+        self.matches.sort_by_key(|m| (m.field, m.start, m.end));
+        let mut merged: Vec<FieldMatch> = Vec::new();
+        for m in self.matches {
+            match merged.last_mut() {
+                Some(last) if last.field == m.field && m.start <= last.end => last.end = last.end.max(m.end),
+                _ => merged.push(m),
+            }
+        }
+        self.matches = merged;
+        self
+    }
+
+    /// Fields with at least one match, in order
+    pub fn fields(&self) -> Vec<SearchField> {
+        let mut fields: Vec<SearchField> = self.matches.iter().map(|m| m.field).collect();
+        fields.dedup();
+        fields
+    }
+}
+
+/// Regex matches reported per field
+const MAX_REGEX_RANGES: usize = 50;
+/// Score of a regex match; regexes don't have a meaningful quality measure
+const REGEX_SCORE: i64 = 32;
+
+impl Term {
+    fn evaluate(&self, item: &ClipItem, haystack: &Haystack) -> Option<Hit> {
+        let filter = |matched: bool| matched.then(Hit::default);
+
+        match self {
+            Term::Text(text) => {
+                let pattern: Vec<char> = text.chars().collect();
+                haystack.best(|field| {
+                    field.best_variant(|variant| {
+                        let m = fuzzy_match(&pattern, &variant.chars)?;
+                        Some((m.score, m.positions))
+                    })
+                })
+            }
+            Term::Phrase(phrase) => {
+                let length = phrase.chars().count();
+                haystack.best(|field| {
+                    field.best_variant(|variant| {
+                        let start = variant.char_index(variant.folded.find(phrase.as_str())?);
+                        let positions: Vec<usize> = (start..start + length).collect();
+                        Some((score_positions(&variant.chars, &positions), positions))
+                    })
+                })
+            }
+            Term::Regex(regex) => haystack.best(|field| {
+                let ranges: Vec<MatchRange> = regex
+                    .find_iter(&field.text)
+                    .filter(|m| !m.is_empty())
+                    .take(MAX_REGEX_RANGES)
+                    .map(|m| MatchRange {
+                        start: field.char_index(m.start()),
+                        end: field.char_index(m.end()),
+                    })
+                    .collect();
+                (!ranges.is_empty() || regex.is_match(&field.text)).then_some((REGEX_SCORE, ranges))
+            }),
+            Term::Type(content_type) => filter(&item.content_type == content_type),
+            Term::Kind(kind) => filter(item.text_kind == Some(*kind)),
+            Term::Selection(selection) => filter(item.selection == *selection),
+            Term::Pinned(pinned) => filter(item.is_pinned == *pinned),
+            Term::Tag(tag) => filter(item.tags.iter().any(|t| t == tag)),
+            Term::Before(ts) => filter(item.timestamp < *ts),
+            Term::After(ts) => filter(item.timestamp >= *ts),
+            Term::Size(cmp, bytes) => {
+                let size = item.content.len() as u64;
+                filter(match cmp {
+                    SizeCmp::Gt => size > *bytes,
+                    SizeCmp::Ge => size >= *bytes,
+                    SizeCmp::Lt => size < *bytes,
+                    SizeCmp::Le => size <= *bytes,
+                    SizeCmp::Eq => size == *bytes,
+                })
+            }
+        }
+    }
+
+    /// Exact SQL for terms on plaintext columns. Text, regex and size need the
+    /// decrypted content and are only checked in memory.
+    fn to_sql(&self) -> Option<(String, Vec<Value>)> {
+        match self {
+            Term::Type(content_type) => Some((
+                "content_type = ?".to_string(),
+                vec![Value::Text(content_type.to_string().to_string())],
+            )),
+            // IS, so NOT keeps images, files and unlabelled clips (NULL) as `matches` does
+            Term::Kind(kind) => Some(("text_kind IS ?".to_string(), vec![Value::Text(kind.as_str().to_string())])),
+            Term::Selection(selection) => Some((
+                "selection = ?".to_string(),
+                vec![Value::Text(selection.as_str().to_string())],
+            )),
+            Term::Pinned(pinned) => Some(("is_pinned = ?".to_string(), vec![Value::Integer(*pinned as i64)])),
+            Term::Tag(tag) => Some((
+                "EXISTS (SELECT 1 FROM clip_tags WHERE clip_tags.clip_id = clips.id AND clip_tags.tag = ?)".to_string(),
+                vec![Value::Text(tag.clone())],
+            )),
+            Term::Before(ts) => Some(("timestamp < ?".to_string(), vec![Value::Integer(*ts)])),
+            Term::After(ts) => Some(("timestamp >= ?".to_string(), vec![Value::Integer(*ts)])),
+            Term::Text(_) | Term::Phrase(_) | Term::Regex(_) | Term::Size(..) => None,
+        }
+    }
+}
+
+fn join_sql(parts: Vec<(String, Vec<Value>)>, operator: &str) -> (String, Vec<Value>) {
+    let mut params = Vec::new();
+    let clauses: Vec<String> = parts
+        .into_iter()
+        .map(|(sql, values)| {
+            params.extend(values);
+            format!("({})", sql)
+        })
+        .collect();
+    (clauses.join(operator), params)
+}
+
+impl Expr {
+    fn evaluate(&self, item: &ClipItem, haystack: &Haystack) -> Option<Hit> {
+        match self {
+            Expr::And(exprs) => exprs
+                .iter()
+                .try_fold(Hit::default(), |hit, e| Some(hit.merge(e.evaluate(item, haystack)?))),
+            // Every matching alternative adds to the score and highlights
+            Expr::Or(exprs) => exprs
+                .iter()
+                .filter_map(|e| e.evaluate(item, haystack))
+                .reduce(Hit::merge),
+            Expr::Not(expr) => match expr.evaluate(item, haystack) {
+                Some(_) => None,
+                None => Some(Hit::default()),
+            },
+            Expr::Term(term) => term.evaluate(item, haystack),
+        }
+    }
+
+    /// SQL equivalent of the whole expression, if every term can be translated
+    fn exact_sql(&self) -> Option<(String, Vec<Value>)> {
+        match self {
+            Expr::And(exprs) => Some(join_sql(exprs.iter().map(Expr::exact_sql).collect::<Option<_>>()?, " AND ")),
+            Expr::Or(exprs) => Some(join_sql(exprs.iter().map(Expr::exact_sql).collect::<Option<_>>()?, " OR ")),
+            Expr::Not(expr) => expr.exact_sql().map(|(sql, params)| (format!("NOT ({})", sql), params)),
+            Expr::Term(term) => term.to_sql(),
+        }
+    }
+
+    /// SQL condition that every matching row satisfies (it may let extra rows through).
+    /// Untranslatable parts of an AND are dropped; anything weaker gives up.
+    fn prefilter_sql(&self) -> Option<(String, Vec<Value>)> {
+        match self {
+            Expr::And(exprs) => {
+                let parts: Vec<_> = exprs.iter().filter_map(Expr::prefilter_sql).collect();
+                (!parts.is_empty()).then(|| join_sql(parts, " AND "))
+            }
+            Expr::Or(exprs) => Some(join_sql(exprs.iter().map(Expr::prefilter_sql).collect::<Option<_>>()?, " OR ")),
+            // Negating a relaxed condition would drop matching rows, so only exact SQL can be negated
+            Expr::Not(_) => self.exact_sql(),
+            Expr::Term(term) => term.to_sql(),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        Self::parse_at(input, Local::now())
+    }
+
+    /// Parse with a fixed "now" for relative dates
+    pub fn parse_at(input: &str, now: DateTime<Local>) -> Result<Self, QueryError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            input_len: input.chars().count(),
+            now,
+        };
+        let expr = parser.parse_or()?;
+
+        if parser.pos < parser.tokens.len() {
+            return error("Unexpected ')'", parser.position());
+        }
+
+        Ok(Self { expr: Some(expr) })
+    }
+
+    /// WHERE condition for the rows that may match. Results must still be checked with `matches`.
+    pub fn sql_filter(&self) -> (String, Vec<Value>) {
+        self.expr
+            .as_ref()
+            .and_then(Expr::prefilter_sql)
+            .unwrap_or_else(|| ("1".to_string(), Vec::new()))
+    }
+
+    /// Match an item, returning its match score and highlight ranges
+    pub fn evaluate(&self, item: &ClipItem) -> Option<Hit> {
+        match &self.expr {
+            Some(expr) => expr.evaluate(item, &Haystack::new(item)).map(Hit::normalized),
+            None => Some(Hit::default()),
+        }
+    }
+
+    pub fn matches(&self, item: &ClipItem) -> bool {
+        self.evaluate(item).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, 19, 15, 30, 0).unwrap()
+    }
+
+    fn parse(input: &str) -> Expr {
+        Query::parse_at(input, now()).unwrap().expr.unwrap()
+    }
+
+    fn parse_err(input: &str) -> QueryError {
+        Query::parse_at(input, now()).unwrap_err()
+    }
+
+    fn text(text: &str) -> ClipItem {
+        ClipItem::new(text.as_bytes().to_vec(), ContentType::Text)
+    }
+
+    #[test]
+    fn test_empty_query_matches_everything() {
+        let query = Query::parse_at("   ", now()).unwrap();
+        assert!(query.expr.is_none());
+        assert!(query.matches(&text("anything")));
+        assert_eq!(query.sql_filter().0, "1");
+    }
+
+    #[test]
+    fn test_words_are_anded() {
+        match parse("Kubectl pods") {
+            Expr::And(terms) => {
+                assert!(matches!(&terms[0], Expr::Term(Term::Text(t)) if t == "kubectl"));
+                assert!(matches!(&terms[1], Expr::Term(Term::Text(t)) if t == "pods"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_or_has_lower_precedence_than_and() {
+        match parse("a b OR c | d") {
+            Expr::Or(terms) => {
+                assert_eq!(terms.len(), 3);
+                assert!(matches!(&terms[0], Expr::And(inner) if inner.len() == 2));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(parse("a AND b"), Expr::And(terms) if terms.len() == 2));
+    }
+
+    #[test]
+    fn test_negation_and_grouping() {
+        assert!(matches!(parse("-type:image"), Expr::Not(inner) if matches!(*inner, Expr::Term(Term::Type(ContentType::Image)))));
+        assert!(matches!(parse("NOT pinned:no"), Expr::Not(inner) if matches!(*inner, Expr::Term(Term::Pinned(false)))));
+        match parse("-(foo OR bar) baz") {
+            Expr::And(terms) => assert!(matches!(&terms[0], Expr::Not(inner) if matches!(**inner, Expr::Or(_)))),
+            other => panic!("unexpected {:?}", other),
+        }
+        // A lone dash is a word, not an operator
+        assert!(matches!(parse("a - b"), Expr::And(terms) if terms.len() == 3));
+    }
+
+    #[test]
+    fn test_phrases_and_quoted_values() {
+        assert!(matches!(parse("\"Get Pods -n\""), Expr::Term(Term::Phrase(p)) if p == "get pods -n"));
+        assert!(matches!(parse("tag:\"Release Notes\""), Expr::Term(Term::Tag(t)) if t == "release notes"));
+        assert!(matches!(parse("\"say \\\"hi\\\"\""), Expr::Term(Term::Phrase(p)) if p == "say \"hi\""));
+    }
+
+    #[test]
+    fn test_regex_and_paths() {
+        assert!(matches!(parse("/^v\\d+\\/api/"), Expr::Term(Term::Regex(r)) if r.as_str() == "^v\\d+/api"));
+        // A path is not a regex
+        assert!(matches!(parse("/usr/bin"), Expr::Term(Term::Text(t)) if t == "/usr/bin"));
+        assert!(matches!(parse("https://example.com"), Expr::Term(Term::Text(t)) if t == "https://example.com"));
+    }
+
+    #[test]
+    fn test_filters() {
+        assert!(matches!(parse("type:IMAGE"), Expr::Term(Term::Type(ContentType::Image))));
+        assert!(matches!(parse("pinned:yes"), Expr::Term(Term::Pinned(true))));
+        assert!(matches!(parse("kind:URL"), Expr::Term(Term::Kind(TextKind::Url))));
+        assert!(matches!(parse("kind:colour"), Expr::Term(Term::Kind(TextKind::Color))));
+        assert!(matches!(parse("selection:Primary"), Expr::Term(Term::Selection(Selection::Primary))));
+        assert!(matches!(parse("size:>1mb"), Expr::Term(Term::Size(SizeCmp::Gt, 1_048_576))));
+        assert!(matches!(parse("size:<=1.5k"), Expr::Term(Term::Size(SizeCmp::Le, 1536))));
+        assert!(matches!(parse("size:100"), Expr::Term(Term::Size(SizeCmp::Ge, 100))));
+    }
+
+    #[test]
+    fn test_dates() {
+        let today = start_of_day(NaiveDate::from_ymd_opt(2026, 10, 19).unwrap()).unwrap();
+        let yesterday = start_of_day(NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()).unwrap();
+        let sept = start_of_day(NaiveDate::from_ymd_opt(2026, 9, 1).unwrap()).unwrap();
+
+        assert!(matches!(parse("after:today"), Expr::Term(Term::After(ts)) if ts == today));
+        assert!(matches!(parse("after:yesterday"), Expr::Term(Term::After(ts)) if ts == yesterday));
+        assert!(matches!(parse("before:2026-09-01"), Expr::Term(Term::Before(ts)) if ts == sept));
+        let week_ago = (now() - Duration::weeks(1)).timestamp();
+        assert!(matches!(parse("after:1w"), Expr::Term(Term::After(ts)) if ts == week_ago));
+
+        // Ages out of chrono's range or below zero are invalid, not a crash
+        for query in ["after:99999999999999d", "before:9999999999w", "after:9223372036854775807h", "after:-3d", "before:-1w"] {
+            assert!(parse_err(query).message.contains("Invalid date"), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(parse_err("\"unterminated").position, 0);
+        assert_eq!(parse_err("foo )").position, 4);
+        assert_eq!(parse_err("(foo").message, "Missing closing parenthesis");
+        assert!(parse_err("()").message.contains("Empty"));
+        assert!(parse_err("foo OR").message.contains("after OR"));
+        assert!(parse_err("OR foo").message.contains("before operator"));
+        assert!(parse_err("-(a) AND").message.contains("after AND"));
+        assert_eq!(parse_err("type:video").position, 5);
+        assert!(parse_err("kind:poem").message.contains("Unknown kind"));
+        assert!(parse_err("pinned:maybe").message.contains("yes or no"));
+        assert!(parse_err("before:someday").message.contains("Invalid date"));
+        assert!(parse_err("size:>big").message.contains("Invalid size"));
+        assert!(parse_err("/[unclosed/").message.contains("regular expression"));
+        assert!(parse_err("tag:").message.contains("Missing value"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut item = text("kubectl get pods -n production");
+        item.tags = vec!["deploy".to_string()];
+
+        let matches = |q: &str| Query::parse_at(q, now()).unwrap().matches(&item);
+        assert!(matches("KUBECTL pods"));
+        assert!(matches("\"get pods\" tag:deploy"));
+        assert!(!matches("\"pods get\""));
+        assert!(matches("nginx OR production"));
+        assert!(!matches("-production"));
+        assert!(matches("/-n \\w+$/"));
+        assert!(matches("type:text pinned:no size:<1kb"));
+        assert!(!matches("type:image | tag:sql"));
+        assert!(!matches("kind:code"));
+        item.text_kind = Some(TextKind::Code);
+        assert!(Query::parse_at("kind:code kubectl", now()).unwrap().matches(&item));
+    }
+
+    fn content(start: usize, end: usize) -> FieldMatch {
+        FieldMatch { field: SearchField::Content, start, end }
+    }
+
+    #[test]
+    fn test_hit_scores_and_ranges() {
+        let item = text("kubectl get pods -n production");
+        let hit = |q: &str| Query::parse_at(q, now()).unwrap().evaluate(&item);
+
+        let exact = hit("pods").unwrap();
+        assert_eq!(exact.matches, vec![content(12, 16)]);
+        assert!(exact.score > hit("kgp").unwrap().score);
+
+        // Ranges of several terms are merged, filters add nothing
+        let both = hit("get pods type:text").unwrap();
+        assert_eq!(both.matches, vec![content(8, 11), content(12, 16)]);
+        assert_eq!(hit("type:text").unwrap(), Hit::default());
+
+        assert_eq!(hit("/pro\\w+/").unwrap().matches, vec![content(20, 30)]);
+        assert_eq!(hit("\"PODS -N\"").unwrap().matches, vec![content(12, 19)]);
+        assert!(hit("prodcution").is_some());
+        assert!(hit("-kubectl").is_none());
+    }
+
+    #[test]
+    fn test_fields_by_content_type() {
+        let evaluate = |item: &ClipItem, q: &str| Query::parse_at(q, now()).unwrap().evaluate(item);
+
+        let html = ClipItem::new(b"<p>Quarterly <b>report</b> &amp; <span class=\"budget\">plan</span></p>".to_vec(), ContentType::Html);
+        let hit = evaluate(&html, "report plan").unwrap();
+        assert_eq!(hit.matches, vec![content(10, 16), content(19, 23)]);
+        // Markup is not searchable
+        assert!(evaluate(&html, "span").is_none());
+        assert!(evaluate(&html, "budget").is_none());
+
+        let rtf = ClipItem::new(br"{\rtf1\ansi{\fonttbl{\f0 Arial;}}\f0 Meeting \b notes\b0\par}".to_vec(), ContentType::Rtf);
+        assert_eq!(evaluate(&rtf, "notes").unwrap().matches, vec![content(8, 13)]);
+        assert!(evaluate(&rtf, "arial").is_none());
+
+        let file = ClipItem::new(b"/home/me/Documents/invoice-2026.pdf\n/tmp/photo.png".to_vec(), ContentType::File);
+        let hit = evaluate(&file, "invoice").unwrap();
+        assert_eq!(hit.fields(), vec![SearchField::FileName]);
+        assert_eq!(hit.matches, vec![FieldMatch { field: SearchField::FileName, start: 0, end: 7 }]);
+        assert_eq!(evaluate(&file, "documents").unwrap().fields(), vec![SearchField::Path]);
+        assert!(evaluate(&file, "photo.png").is_some());
+
+        let mut image = ClipItem::new(vec![0x89, b'P', b'N', b'G'], ContentType::Image);
+        assert!(evaluate(&image, "png").is_none());
+        image.note = Some("Network diagram".to_string());
+        assert_eq!(evaluate(&image, "diagram").unwrap().matches, vec![FieldMatch { field: SearchField::Note, start: 8, end: 15 }]);
+    }
+
+    #[test]
+    fn test_sql_prefilter() {
+        let sql = |q: &str| Query::parse_at(q, now()).unwrap().sql_filter();
+
+        let (clause, params) = sql("type:image pinned:yes");
+        assert_eq!(clause, "(content_type = ?) AND (is_pinned = ?)");
+        assert_eq!(params, vec![Value::Text("image".into()), Value::Integer(1)]);
+
+        // Free text is checked in memory, the rest still narrows the SQL
+        assert_eq!(sql("kubectl tag:deploy").0.matches("tag = ?").count(), 1);
+        assert_eq!(sql("kubectl").0, "1");
+
+        // An OR with an untranslatable branch can't be narrowed
+        assert_eq!(sql("type:image OR kubectl").0, "1");
+        assert_eq!(sql("type:image OR type:file").0, "(content_type = ?) OR (content_type = ?)");
+        assert_eq!(sql("kind:json").1, vec![Value::Text("json".into())]);
+
+        // Only exact conditions are negated
+        assert_eq!(sql("-type:image").0, "NOT (content_type = ?)");
+        assert_eq!(sql("-(type:image kubectl)").0, "1");
+    }
+}
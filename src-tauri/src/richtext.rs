@@ -0,0 +1,348 @@
+// Plain-text rendering of HTML and RTF clips, for search and previews
+//
+// These are deliberately small converters, not full parsers: they keep the
+// visible text and line structure and drop everything else.
+
+/// Tags that start a new line in the rendered text
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption", "figure",
+    "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav", "ol", "p", "pre",
+    "section", "table", "tr", "ul",
+];
+
+/// Elements whose content is not visible text
+const HIDDEN_TAGS: &[&str] = &["head", "script", "style", "template", "title"];
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ensp" | "emsp" | "thinsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "times" => '×',
+        _ => return None,
+    })
+}
+
+/// Collapse runs of whitespace within lines and drop blank lines
+fn tidy_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render HTML (including a Windows CF_HTML payload with its header) as plain text
+pub fn html_to_text(html: &str) -> String {
+    // CF_HTML starts with "Version:0.9\r\nStartHTML:..." before the markup
+    let html = if html.starts_with("Version:") {
+        html.find('<').map(|start| &html[start..]).unwrap_or("")
+    } else {
+        html
+    };
+
+    let mut out = String::with_capacity(html.len() / 2);
+    let mut hidden: Option<String> = None;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+
+        if rest.starts_with('<') {
+            // An unterminated tag at the end is dropped
+            let Some(end) = rest.find('>') else { break };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with('/');
+            let name: String = tag
+                .trim_start_matches('/')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase();
+
+            match &hidden {
+                Some(hidden_name) if closing && *hidden_name == name => hidden = None,
+                Some(_) => {}
+                None if !closing && HIDDEN_TAGS.contains(&name.as_str()) && !tag.ends_with('/') => hidden = Some(name),
+                None if BLOCK_TAGS.contains(&name.as_str()) => out.push('\n'),
+                None if name == "td" || name == "th" => out.push('\t'),
+                None => {}
+            }
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        let mut consumed = c.len_utf8();
+
+        if hidden.is_none() {
+            if c == '&' {
+                let entity_end = rest[1..].find(|c: char| c == ';' || c == '<' || c.is_whitespace()).map(|i| i + 1);
+                match entity_end.filter(|&end| rest[end..].starts_with(';')).and_then(|end| {
+                    decode_entity(&rest[1..end]).map(|decoded| (decoded, end + 1))
+                }) {
+                    Some((decoded, length)) => {
+                        out.push(decoded);
+                        consumed = length;
+                    }
+                    None => out.push('&'),
+                }
+            } else {
+                // Source line breaks are just whitespace in HTML
+                out.push(if c == '\n' || c == '\r' { ' ' } else { c });
+            }
+        }
+
+        rest = &rest[consumed..];
+    }
+
+    tidy_lines(&out)
+}
+
+/// RTF destinations whose content is not document text
+const SKIPPED_DESTINATIONS: &[&str] = &[
+    "colortbl", "datastore", "fldinst", "fonttbl", "footer", "footerf", "footerl", "footerr", "header",
+    "headerf", "headerl", "headerr", "info", "latentstyles", "listoverridetable", "listtable", "object",
+    "pict", "rsidtbl", "stylesheet", "themedata", "colorschememapping", "generator", "xmlnstbl",
+];
+
+/// Windows-1252 characters in 0x80..0xA0 (other bytes map to the same Latin-1 code point)
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// The first char boundary at or after byte `i`, so skipping bytes never splits a character
+fn char_boundary(text: &str, mut i: usize) -> usize {
+    if i >= text.len() {
+        return text.len();
+    }
+    while !text.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+struct RtfGroup {
+    skip: bool,
+    /// Number of fallback characters after a `\uN` (`\ucN`)
+    unicode_skip: usize,
+}
+
+/// Render RTF as plain text. Unicode (`\uN`) is decoded; `\'hh` bytes are decoded
+/// as Windows-1252 and dropped for other code pages, whose writers also emit `\uN`.
+pub fn rtf_to_text(rtf: &str) -> String {
+    let bytes = rtf.as_bytes();
+    let mut out = String::with_capacity(rtf.len() / 4);
+    let mut stack: Vec<RtfGroup> = Vec::new();
+    let mut group = RtfGroup { skip: false, unicode_skip: 1 };
+    let mut ansi_1252 = true;
+    // Fallback characters still to skip after a \uN
+    let mut pending_skip = 0usize;
+    // Always on a char boundary: skips over raw bytes are rounded up to the next one
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => {
+                stack.push(RtfGroup { skip: group.skip, unicode_skip: group.unicode_skip });
+                pending_skip = 0;
+                i += 1;
+            }
+            b'}' => {
+                group = stack.pop().unwrap_or(RtfGroup { skip: false, unicode_skip: 1 });
+                pending_skip = 0;
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&next) = bytes.get(i) else { break };
+
+                if next.is_ascii_alphabetic() {
+                    let start = i;
+                    while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let word = &rtf[start..i];
+
+                    let param_start = i;
+                    if bytes.get(i) == Some(&b'-') {
+                        i += 1;
+                    }
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let param: Option<i32> = rtf[param_start..i].parse().ok();
+                    // A single space delimits the control word
+                    if bytes.get(i) == Some(&b' ') {
+                        i += 1;
+                    }
+
+                    if SKIPPED_DESTINATIONS.contains(&word) {
+                        group.skip = true;
+                        continue;
+                    }
+                    if word == "ansicpg" {
+                        ansi_1252 = param == Some(1252);
+                    }
+                    if word == "uc" {
+                        group.unicode_skip = param.unwrap_or(1).max(0) as usize;
+                    }
+                    if word == "bin" {
+                        i = char_boundary(rtf, i.saturating_add(param.unwrap_or(0).max(0) as usize));
+                    }
+                    if group.skip {
+                        continue;
+                    }
+
+                    if pending_skip > 0 {
+                        pending_skip -= 1;
+                        continue;
+                    }
+
+                    match word {
+                        "par" | "line" | "sect" | "page" | "row" => out.push('\n'),
+                        "tab" | "cell" => out.push('\t'),
+                        "emdash" => out.push('—'),
+                        "endash" => out.push('–'),
+                        "bullet" => out.push('•'),
+                        "lquote" => out.push('‘'),
+                        "rquote" => out.push('’'),
+                        "ldblquote" => out.push('“'),
+                        "rdblquote" => out.push('”'),
+                        "u" => {
+                            if let Some(code) = param {
+                                let code = if code < 0 { code + 65536 } else { code } as u32;
+                                out.extend(char::from_u32(code));
+                                pending_skip = group.unicode_skip;
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                i = char_boundary(rtf, i + 1);
+                match next {
+                    // `{\*\dest ...}`: an optional destination the reader doesn't know
+                    b'*' => group.skip = true,
+                    b'\'' => {
+                        let hex = rtf.get(i..i + 2).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                        i = char_boundary(rtf, i + 2);
+                        if group.skip {
+                            continue;
+                        }
+                        if pending_skip > 0 {
+                            pending_skip -= 1;
+                            continue;
+                        }
+                        if let (Some(byte), true) = (hex, ansi_1252) {
+                            out.push(match byte {
+                                0x80..=0x9f => CP1252_HIGH[(byte - 0x80) as usize],
+                                _ => byte as char,
+                            });
+                        }
+                    }
+                    _ if group.skip => {}
+                    b'\\' | b'{' | b'}' => out.push(next as char),
+                    b'~' => out.push(' '),
+                    b'_' => out.push('-'),
+                    b'\n' | b'\r' => out.push('\n'),
+                    _ => {}
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            _ => {
+                let c = rtf[i..].chars().next().unwrap_or_default();
+                i += c.len_utf8();
+                if group.skip {
+                    continue;
+                }
+                if pending_skip > 0 {
+                    pending_skip -= 1;
+                    continue;
+                }
+                out.push(c);
+            }
+        }
+    }
+
+    tidy_lines(&out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><title>Page</title><style>p { color: red }</style></head>
+            <body><h1>Release&nbsp;notes</h1><!-- hidden --><p>Fixed <b>crash</b> &amp; improved
+            <a href="https://example.com">search</a>.</p><ul><li>One</li><li>Two &#x2713;</li></ul>
+            <script>alert("x")</script><p>AT&T &copy 2026</p></body></html>"#;
+
+        assert_eq!(
+            html_to_text(html),
+            "Release notes\nFixed crash & improved search.\nOne\nTwo ✓\nAT&T &copy 2026"
+        );
+    }
+
+    #[test]
+    fn test_cf_html_header_is_skipped() {
+        let cf_html = "Version:0.9\r\nStartHTML:00000097\r\nEndHTML:00000170\r\n<html><body><!--StartFragment--><i>服务器</i> ok<!--EndFragment--></body></html>";
+        assert_eq!(html_to_text(cf_html), "服务器 ok");
+    }
+
+    #[test]
+    fn test_rtf_to_text() {
+        let rtf = r#"{\rtf1\ansi\ansicpg1252\deff0{\fonttbl{\f0\fswiss Helvetica;}}{\colortbl;\red255\green0\blue0;}
+{\*\generator Riched20 10.0;}\f0\fs24 Hello {\b bold} world\par
+Caf\'e9 \u8212? and \u26381\'3f\u21153\'3f\par
+{\field{\*\fldinst HYPERLINK "https://example.com"}{\fldrslt link text}}\tab end\}}"#;
+
+        assert_eq!(rtf_to_text(rtf), "Hello bold world\nCafé — and 服务\nlink text end}");
+    }
+
+    #[test]
+    fn test_rtf_other_codepage_uses_unicode() {
+        // GBK bytes are the fallback for each \uN
+        let rtf = r"{\rtf1\ansi\ansicpg936\uc2 \u20013\'d6\'d0\u25991\'ce\'c4 ok}";
+        assert_eq!(rtf_to_text(rtf), "中文 ok");
+    }
+
+    #[test]
+    fn test_rtf_skips_never_split_characters() {
+        // A control symbol, a short \'hh and \binN that end inside a multi-byte character
+        assert_eq!(rtf_to_text(r"{\rtf1 \é ok}"), "ok");
+        assert_eq!(rtf_to_text(r"{\rtf1 \'aé x ok}"), "x ok");
+        assert_eq!(rtf_to_text(r"{\rtf1 {\pict\bin1 éé} ok}"), "ok");
+    }
+}
@@ -0,0 +1,333 @@
+// PRIMARY selection: capture it and keep it in sync with the clipboard (Linux)
+//
+// X11 has a second clipboard, PRIMARY, holding whatever text is selected and pasted
+// with a middle click. It doesn't announce changes and changes with every mouse move
+// while a selection is dragged, so it is polled and a selection only counts once it has
+// stayed the same for the debounce time. A settled selection is captured (marked as
+// PRIMARY) and, when syncing, copied to the clipboard; text copied to the clipboard is
+// selected in turn, the way Klipper and Parcellite keep the two together. Only text is
+// synced, and synced copies are never captured a second time. Neither are secrets a
+// password manager marked nor copies from apps whose copies aren't stored: those stay
+// where they were made.
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::capture::{CaptureEvents, CapturePipeline, ClipboardBackend};
+use crate::monitor::write_uncaptured;
+use crate::source_app::{is_app_allowed, SourceApp};
+use crate::storage::{ClipFormat, ContentType, Selection};
+
+/// How often PRIMARY is looked at
+pub const SELECTION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelectionOptions {
+    /// Store settled selections in history
+    pub capture: bool,
+    /// Copy settled selections to the clipboard and select what is copied
+    pub sync: bool,
+    /// How long a selection must stay unchanged before it counts
+    pub debounce: Duration,
+    /// Apps whose copies and selections may be synced; all when empty
+    pub allowed_apps: Vec<String>,
+    /// Apps whose copies and selections are never synced
+    pub excluded_apps: Vec<String>,
+}
+
+impl SelectionOptions {
+    /// Whether there is anything to watch PRIMARY for
+    pub fn is_enabled(&self) -> bool {
+        self.capture || self.sync
+    }
+}
+
+/// PRIMARY as a clipboard that only changes once a selection settles. Run by a
+/// `CaptureMonitor`, whose pipeline stores the settled selections; syncing happens
+/// while waiting for them.
+pub struct SelectionWatcher<P, C, E> {
+    primary: P,
+    clipboard: C,
+    /// Pipeline capturing the clipboard, which must not capture synced copies
+    clipboard_pipeline: Arc<Mutex<CapturePipeline<E>>>,
+    options: SelectionOptions,
+    /// Selected text that hasn't settled yet, and since when it is selected
+    pending: Option<(String, Instant)>,
+    /// The last selection that settled or was synced from the clipboard
+    settled: Option<String>,
+    /// The clipboard text as last seen or synced
+    clipboard_text: Option<String>,
+}
+
+fn read_text(backend: &mut impl ClipboardBackend) -> Option<String> {
+    backend
+        .read_formats()
+        .into_iter()
+        .find(|format| format.content_type == ContentType::Text)
+        .map(|format| String::from_utf8_lossy(&format.data).into_owned())
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// Whether what `backend` holds may be synced to the other selection: the app it comes
+/// from isn't denied and didn't mark it as a secret
+fn may_sync(backend: &mut impl ClipboardBackend, options: &SelectionOptions) -> bool {
+    !backend.is_concealed() && is_app_allowed(backend.source_app().as_ref(), &options.allowed_apps, &options.excluded_apps)
+}
+
+fn write_text(backend: &mut impl ClipboardBackend, text: &str) -> Result<(), String> {
+    backend.write_formats(&[ClipFormat { content_type: ContentType::Text, data: text.as_bytes().to_vec() }])
+}
+
+impl<P: ClipboardBackend, C: ClipboardBackend, E: CaptureEvents> SelectionWatcher<P, C, E> {
+    /// What is selected and copied when watching starts counts as handled
+    pub fn new(
+        mut primary: P,
+        mut clipboard: C,
+        clipboard_pipeline: Arc<Mutex<CapturePipeline<E>>>,
+        options: SelectionOptions,
+    ) -> Self {
+        let settled = read_text(&mut primary);
+        let clipboard_text = read_text(&mut clipboard);
+        Self { primary, clipboard, clipboard_pipeline, options, pending: None, settled, clipboard_text }
+    }
+
+    /// Select what was copied to the clipboard since last time
+    fn sync_from_clipboard(&mut self) {
+        let text = read_text(&mut self.clipboard);
+        if text.is_none() || text == self.clipboard_text {
+            return;
+        }
+        self.clipboard_text = text.clone();
+        let Some(text) = text.filter(|text| self.settled.as_ref() != Some(text)) else {
+            return;
+        };
+        if !may_sync(&mut self.clipboard, &self.options) {
+            log::info!("🙈 Not selecting a copy that isn't to be recorded");
+            return;
+        }
+
+        match write_text(&mut self.primary, &text) {
+            Ok(()) => {
+                self.pending = None;
+                self.settled = Some(text);
+            }
+            Err(e) => log::warn!("⚠️ Failed to select the copied text: {}", e),
+        }
+    }
+
+    /// Copy a settled selection to the clipboard, without capturing the copy
+    fn sync_to_clipboard(&mut self, text: &str) {
+        if self.clipboard_text.as_deref() == Some(text) {
+            return;
+        }
+        if !may_sync(&mut self.primary, &self.options) {
+            log::info!("🙈 Not copying a selection that isn't to be recorded");
+            return;
+        }
+        match write_uncaptured(&self.clipboard_pipeline, &mut self.clipboard, |clipboard| write_text(clipboard, text)) {
+            Ok(()) => self.clipboard_text = Some(text.to_string()),
+            Err(e) => log::warn!("⚠️ Failed to copy the selection to the clipboard: {}", e),
+        }
+    }
+}
+
+impl<P: ClipboardBackend, C: ClipboardBackend, E: CaptureEvents> ClipboardBackend for SelectionWatcher<P, C, E> {
+    fn read_formats(&mut self) -> Vec<ClipFormat> {
+        self.primary.read_formats()
+    }
+
+    fn write_formats(&mut self, formats: &[ClipFormat]) -> Result<(), String> {
+        self.primary.write_formats(formats)
+    }
+
+    /// Reports a change once a new selection has settled and is to be captured
+    fn wait_for_change(&mut self, timeout: Duration) -> bool {
+        // Selections aren't announced: look every time
+        self.primary.wait_for_change(timeout);
+        if self.options.sync {
+            self.sync_from_clipboard();
+        }
+
+        let Some(text) = read_text(&mut self.primary) else {
+            self.pending = None;
+            return false;
+        };
+        if self.settled.as_ref() == Some(&text) {
+            self.pending = None;
+            return false;
+        }
+
+        let since = match &self.pending {
+            Some((pending, since)) if *pending == text => *since,
+            _ => {
+                let now = Instant::now();
+                self.pending = Some((text.clone(), now));
+                now
+            }
+        };
+        if since.elapsed() < self.options.debounce {
+            return false;
+        }
+
+        self.pending = None;
+        if self.options.sync {
+            self.sync_to_clipboard(&text);
+        }
+        self.settled = Some(text);
+        self.options.capture
+    }
+
+    fn is_concealed(&mut self) -> bool {
+        self.primary.is_concealed()
+    }
+
+    fn source_app(&mut self) -> Option<SourceApp> {
+        self.primary.source_app()
+    }
+
+    fn selection(&self) -> Selection {
+        Selection::Primary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_encoding::BASE64;
+
+    use crate::capture::testing::{MemoryClipboard, RecordedEvents};
+    use crate::capture::CaptureOptions;
+    use crate::storage::ClipStorage;
+
+    const DEBOUNCE: Duration = Duration::from_millis(30);
+
+    fn text(text: &str) -> MemoryClipboard {
+        MemoryClipboard { text: Some(text.to_string()), ..Default::default() }
+    }
+
+    struct Fixture {
+        watcher: SelectionWatcher<MemoryClipboard, MemoryClipboard, RecordedEvents>,
+        pipeline: CapturePipeline<RecordedEvents>,
+        events: RecordedEvents,
+        clipboard_pipeline: Arc<Mutex<CapturePipeline<RecordedEvents>>>,
+        clipboard_events: RecordedEvents,
+    }
+
+    impl Fixture {
+        fn new(capture: bool, sync: bool) -> Self {
+            let storage = Arc::new(Mutex::new(ClipStorage::new(":memory:", None).unwrap()));
+            let pipeline = |events: &RecordedEvents| CapturePipeline::new(storage.clone(), Arc::default(), events.clone());
+            let (events, clipboard_events) = (RecordedEvents::default(), RecordedEvents::default());
+            let clipboard_pipeline = Arc::new(Mutex::new(pipeline(&clipboard_events)));
+            let options = SelectionOptions {
+                capture,
+                sync,
+                debounce: DEBOUNCE,
+                excluded_apps: vec!["KeePassXC".to_string()],
+                ..Default::default()
+            };
+            let watcher = SelectionWatcher::new(text("old"), text("old"), clipboard_pipeline.clone(), options);
+            Self { watcher, pipeline: pipeline(&events), events, clipboard_pipeline, clipboard_events }
+        }
+
+        /// One round of the monitor thread
+        fn poll(&mut self) {
+            if self.watcher.wait_for_change(Duration::ZERO) {
+                self.pipeline.process(&mut self.watcher, &CaptureOptions { max_history_items: 100, ..Default::default() });
+            }
+            // The clipboard monitor runs too
+            let mut clipboard_pipeline = self.clipboard_pipeline.lock().unwrap();
+            if self.watcher.clipboard.wait_for_change(Duration::ZERO) {
+                clipboard_pipeline.process(&mut self.watcher.clipboard, &CaptureOptions { max_history_items: 100, ..Default::default() });
+            }
+        }
+
+        fn settle(&mut self) {
+            self.poll();
+            std::thread::sleep(DEBOUNCE * 2);
+            self.poll();
+        }
+
+        fn captured(events: &RecordedEvents) -> Vec<(String, Selection)> {
+            let events = events.0.lock().unwrap();
+            events
+                .iter()
+                .map(|item| (String::from_utf8(BASE64.decode(item.content.as_bytes()).unwrap()).unwrap(), item.selection))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_selections_settle_before_capture() {
+        let mut fixture = Fixture::new(true, false);
+        // What was selected before watching started isn't captured
+        fixture.settle();
+        assert!(Fixture::captured(&fixture.events).is_empty());
+
+        // Dragging grows the selection on every poll; only where it stops is captured
+        for selected in ["q", "qui", "quick br", "quick brown"] {
+            fixture.watcher.primary.copy(text(selected));
+            fixture.poll();
+        }
+        assert!(fixture.events.0.lock().unwrap().is_empty());
+        fixture.settle();
+        assert_eq!(Fixture::captured(&fixture.events), vec![("quick brown".to_string(), Selection::Primary)]);
+
+        // Neither are empty selections
+        fixture.watcher.primary.copy(text("  "));
+        fixture.settle();
+        assert_eq!(Fixture::captured(&fixture.events).len(), 1);
+        // Without syncing the clipboard is left alone
+        assert_eq!(fixture.watcher.clipboard.text.as_deref(), Some("old"));
+    }
+
+    #[test]
+    fn test_sync_selections() {
+        let mut fixture = Fixture::new(false, true);
+
+        // A settled selection is copied, but syncing alone doesn't add to history
+        fixture.watcher.primary.copy(text("selected"));
+        fixture.settle();
+        assert_eq!(fixture.watcher.clipboard.text.as_deref(), Some("selected"));
+        assert!(Fixture::captured(&fixture.events).is_empty());
+        assert!(Fixture::captured(&fixture.clipboard_events).is_empty());
+
+        // A copy is selected; the clipboard monitor captures it as usual, and only once
+        fixture.watcher.clipboard.copy(text("copied"));
+        fixture.settle();
+        fixture.settle();
+        assert_eq!(fixture.watcher.primary.text.as_deref(), Some("copied"));
+        assert_eq!(Fixture::captured(&fixture.clipboard_events), vec![("copied".to_string(), Selection::Clipboard)]);
+        assert!(Fixture::captured(&fixture.events).is_empty());
+
+        // Only text is synced
+        fixture.watcher.clipboard.copy(MemoryClipboard { files: Some(vec!["/tmp/a.png".into()]), ..Default::default() });
+        fixture.settle();
+        assert_eq!(fixture.watcher.primary.text.as_deref(), Some("copied"));
+    }
+
+    #[test]
+    fn test_secrets_are_not_synced() {
+        let mut fixture = Fixture::new(false, true);
+        let keepass = || Some(SourceApp { name: "keepassxc".to_string(), pid: None });
+
+        // Copies a password manager marked, or made in a denied app, aren't selected
+        fixture.watcher.clipboard.copy(MemoryClipboard { concealed: true, ..text("hunter2") });
+        fixture.settle();
+        assert_eq!(fixture.watcher.primary.text.as_deref(), Some("old"));
+        fixture.watcher.clipboard.copy(MemoryClipboard { source_app: keepass(), ..text("correct horse") });
+        fixture.settle();
+        assert_eq!(fixture.watcher.primary.text.as_deref(), Some("old"));
+
+        // Nor are such selections copied
+        fixture.watcher.primary.copy(MemoryClipboard { concealed: true, ..text("battery staple") });
+        fixture.settle();
+        fixture.watcher.primary.copy(MemoryClipboard { source_app: keepass(), ..text("s3cret") });
+        fixture.settle();
+        assert_eq!(fixture.watcher.clipboard.text.as_deref(), Some("correct horse"));
+
+        // Later copies are synced again
+        fixture.watcher.clipboard.copy(text("notes"));
+        fixture.settle();
+        assert_eq!(fixture.watcher.primary.text.as_deref(), Some("notes"));
+    }
+}
@@ -1,8 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri_plugin_store::StoreExt;
 use tauri::AppHandle;
 
+use crate::capture::CaptureOptions;
+use crate::capture_rules::{CaptureRule, ExpiryRule, RuleAction, default_capture_rules, migrate_expiry_rules};
+use crate::selection::SelectionOptions;
+use crate::storage::SortMode;
+use crate::transform::{TransformRule, default_transform_rules};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Settings {
@@ -13,9 +20,34 @@ pub struct Settings {
     pub store_original_image: bool,
     pub max_pinned_in_tray: usize,
     pub max_recent_in_tray: usize,
+    pub tray_sort_mode: SortMode,
     pub custom_data_path: Option<String>,
     pub enable_autostart: bool,
     pub locale: String,
+    pub transform_rules: Vec<TransformRule>,
+    pub capture_rules: Vec<CaptureRule>,
+    /// What happens to copies password managers mark as not to be recorded
+    pub password_manager_action: RuleAction,
+    /// Flag clips holding card numbers, tokens, keys or passwords as sensitive
+    pub detect_sensitive: bool,
+    /// Mask sensitive clips in the tray and window until shown
+    pub mask_sensitive: bool,
+    /// Clear the clipboard this many seconds after a sensitive clip is pasted from
+    /// history, unless the clip sets its own delay
+    pub clear_sensitive_after_secs: Option<u32>,
+    /// Apps (WM_CLASS, executable or app name) whose copies are stored; all when empty
+    pub allowed_apps: Vec<String>,
+    /// Apps whose copies are never stored, such as password managers
+    pub excluded_apps: Vec<String>,
+    /// Also capture the PRIMARY selection (selected text) on Linux
+    pub capture_primary: bool,
+    /// Keep the clipboard and the PRIMARY selection holding the same text on Linux
+    pub sync_selections: bool,
+    /// How long a selection must stay unchanged before it is captured or synced
+    pub selection_debounce_ms: u64,
+    /// Serve captured clips from ClipMan so they survive the app they were copied from.
+    /// X11 only: on Wayland just apps running under XWayland see the kept clipboard.
+    pub persist_clipboard: bool,
 }
 
 impl Default for Settings {
@@ -28,9 +60,51 @@ impl Default for Settings {
             store_original_image: false,
             max_pinned_in_tray: 5,
             max_recent_in_tray: 20,
+            tray_sort_mode: SortMode::Recent,
             custom_data_path: None,
             enable_autostart: false,
             locale: "zh-CN".to_string(),
+            transform_rules: default_transform_rules(),
+            capture_rules: default_capture_rules(),
+            password_manager_action: RuleAction::Skip,
+            detect_sensitive: true,
+            mask_sensitive: true,
+            clear_sensitive_after_secs: None,
+            allowed_apps: Vec::new(),
+            excluded_apps: Vec::new(),
+            capture_primary: false,
+            sync_selections: false,
+            selection_debounce_ms: 500,
+            persist_clipboard: false,
+        }
+    }
+}
+
+impl Settings {
+    /// The settings the capture pipeline applies to new clips
+    pub fn capture_options(&self) -> CaptureOptions {
+        CaptureOptions {
+            transform_rules: self.transform_rules.clone(),
+            capture_rules: self.capture_rules.clone(),
+            password_manager_action: self.password_manager_action.clone(),
+            detect_sensitive: self.detect_sensitive,
+            allowed_apps: self.allowed_apps.clone(),
+            excluded_apps: self.excluded_apps.clone(),
+            max_history_items: self.max_history_items,
+            store_original_image: self.store_original_image,
+            // Elsewhere the clipboard keeps its contents when apps exit
+            persist_clipboard: cfg!(target_os = "linux") && self.persist_clipboard,
+        }
+    }
+
+    /// What the PRIMARY selection is watched for
+    pub fn selection_options(&self) -> SelectionOptions {
+        SelectionOptions {
+            capture: self.capture_primary,
+            sync: self.sync_selections,
+            debounce: Duration::from_millis(self.selection_debounce_ms),
+            allowed_apps: self.allowed_apps.clone(),
+            excluded_apps: self.excluded_apps.clone(),
         }
     }
 }
@@ -93,6 +167,12 @@ impl SettingsManager {
             }
         }
 
+        if let Some(sort_mode) = store.get("tray_sort_mode") {
+            if let Ok(mode) = serde_json::from_value::<SortMode>(sort_mode) {
+                self.settings.lock().unwrap().tray_sort_mode = mode;
+            }
+        }
+
         if let Some(custom_path) = store.get("custom_data_path") {
             if let Some(path) = custom_path.as_str() {
                 self.settings.lock().unwrap().custom_data_path = Some(path.to_string());
@@ -111,6 +191,97 @@ impl SettingsManager {
             }
         }
 
+        if let Some(rules) = store.get("transform_rules") {
+            match serde_json::from_value::<Vec<TransformRule>>(rules) {
+                Ok(rules) => self.settings.lock().unwrap().transform_rules = rules,
+                Err(e) => log::warn!("Invalid transform_rules in settings, using defaults: {}", e),
+            }
+        }
+
+        if let Some(rules) = store.get("capture_rules") {
+            match serde_json::from_value::<Vec<CaptureRule>>(rules) {
+                Ok(rules) => self.settings.lock().unwrap().capture_rules = rules,
+                Err(e) => log::warn!("Invalid capture_rules in settings, using defaults: {}", e),
+            }
+        }
+
+        // Expiry rules are capture rules now, with the expire action
+        if let Some(rules) = store.get("expiry_rules") {
+            match serde_json::from_value::<Vec<ExpiryRule>>(rules) {
+                Ok(rules) => {
+                    let mut settings = self.settings.lock().unwrap();
+                    migrate_expiry_rules(&mut settings.capture_rules, rules);
+                    store.set("capture_rules", serde_json::json!(settings.capture_rules));
+                }
+                Err(e) => log::warn!("Invalid expiry_rules in settings, dropping them: {}", e),
+            }
+            store.delete("expiry_rules");
+            if let Err(e) = store.save() {
+                log::warn!("Failed to save migrated expiry rules: {}", e);
+            }
+        }
+
+        if let Some(action) = store.get("password_manager_action") {
+            match serde_json::from_value::<RuleAction>(action) {
+                Ok(action) => self.settings.lock().unwrap().password_manager_action = action,
+                Err(e) => log::warn!("Invalid password_manager_action in settings, using default: {}", e),
+            }
+        }
+
+        if let Some(detect) = store.get("detect_sensitive") {
+            if let Some(b) = detect.as_bool() {
+                self.settings.lock().unwrap().detect_sensitive = b;
+            }
+        }
+
+        if let Some(mask) = store.get("mask_sensitive") {
+            if let Some(b) = mask.as_bool() {
+                self.settings.lock().unwrap().mask_sensitive = b;
+            }
+        }
+
+        if let Some(clear_after) = store.get("clear_sensitive_after_secs") {
+            self.settings.lock().unwrap().clear_sensitive_after_secs = clear_after.as_u64().map(|n| n as u32);
+        }
+
+        if let Some(apps) = store.get("allowed_apps") {
+            match serde_json::from_value::<Vec<String>>(apps) {
+                Ok(apps) => self.settings.lock().unwrap().allowed_apps = apps,
+                Err(e) => log::warn!("Invalid allowed_apps in settings, using defaults: {}", e),
+            }
+        }
+
+        if let Some(apps) = store.get("excluded_apps") {
+            match serde_json::from_value::<Vec<String>>(apps) {
+                Ok(apps) => self.settings.lock().unwrap().excluded_apps = apps,
+                Err(e) => log::warn!("Invalid excluded_apps in settings, using defaults: {}", e),
+            }
+        }
+
+        if let Some(capture) = store.get("capture_primary") {
+            if let Some(b) = capture.as_bool() {
+                self.settings.lock().unwrap().capture_primary = b;
+            }
+        }
+
+        if let Some(sync) = store.get("sync_selections") {
+            if let Some(b) = sync.as_bool() {
+                self.settings.lock().unwrap().sync_selections = b;
+            }
+        }
+
+        if let Some(debounce) = store.get("selection_debounce_ms") {
+            if let Some(n) = debounce.as_u64() {
+                self.settings.lock().unwrap().selection_debounce_ms = n;
+            }
+        }
+
+        if let Some(persist) = store.get("persist_clipboard") {
+            if let Some(b) = persist.as_bool() {
+                self.settings.lock().unwrap().persist_clipboard = b;
+            }
+        }
+
         log::info!("Settings loaded: {:?}", self.settings.lock().unwrap());
         Ok(())
     }
@@ -128,9 +299,22 @@ impl SettingsManager {
         store.set("store_original_image", serde_json::json!(settings.store_original_image));
         store.set("max_pinned_in_tray", serde_json::json!(settings.max_pinned_in_tray));
         store.set("max_recent_in_tray", serde_json::json!(settings.max_recent_in_tray));
+        store.set("tray_sort_mode", serde_json::json!(settings.tray_sort_mode));
         store.set("custom_data_path", serde_json::json!(settings.custom_data_path));
         store.set("enable_autostart", serde_json::json!(settings.enable_autostart));
         store.set("locale", serde_json::json!(settings.locale));
+        store.set("transform_rules", serde_json::json!(settings.transform_rules));
+        store.set("capture_rules", serde_json::json!(settings.capture_rules));
+        store.set("password_manager_action", serde_json::json!(settings.password_manager_action));
+        store.set("detect_sensitive", serde_json::json!(settings.detect_sensitive));
+        store.set("mask_sensitive", serde_json::json!(settings.mask_sensitive));
+        store.set("clear_sensitive_after_secs", serde_json::json!(settings.clear_sensitive_after_secs));
+        store.set("allowed_apps", serde_json::json!(settings.allowed_apps));
+        store.set("excluded_apps", serde_json::json!(settings.excluded_apps));
+        store.set("capture_primary", serde_json::json!(settings.capture_primary));
+        store.set("sync_selections", serde_json::json!(settings.sync_selections));
+        store.set("selection_debounce_ms", serde_json::json!(settings.selection_debounce_ms));
+        store.set("persist_clipboard", serde_json::json!(settings.persist_clipboard));
 
         store.save().map_err(|e| format!("Failed to save store: {}", e))?;
 
@@ -0,0 +1,138 @@
+// The app a clip was copied from
+//
+// Clipboards don't say who put the contents there, so the app of the active window
+// at capture time stands in for it: on X11 the `_NET_ACTIVE_WINDOW`'s WM_CLASS and
+// PID, on Windows the foreground window's executable, on macOS the frontmost app.
+// Where none of these is available (Wayland) the source is unknown.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceApp {
+    /// WM_CLASS class, executable name or app name, as the allow and deny lists use it
+    pub name: String,
+    pub pid: Option<u32>,
+}
+
+/// Whether clips copied from `app` are stored: it isn't on the deny list and, if there
+/// is an allow list, it is on it. Names are compared case-insensitively. Clips from an
+/// unknown source are always stored, since neither list can say anything about them.
+pub fn is_app_allowed(app: Option<&SourceApp>, allowed: &[String], excluded: &[String]) -> bool {
+    let Some(app) = app else {
+        return true;
+    };
+    let listed = |names: &[String]| names.iter().any(|name| name.trim().eq_ignore_ascii_case(&app.name));
+    !listed(excluded) && (allowed.is_empty() || listed(allowed))
+}
+
+/// The app of the active window, which made the latest copy
+pub fn active_app() -> Option<SourceApp> {
+    #[cfg(target_os = "linux")]
+    return crate::x11_clipboard::active_window_app().unwrap_or_else(|e| {
+        log::debug!("Failed to read the active window: {}", e);
+        None
+    });
+
+    #[cfg(target_os = "windows")]
+    return win32::foreground_app();
+
+    #[cfg(target_os = "macos")]
+    return macos::frontmost_app();
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    None
+}
+
+#[cfg(target_os = "windows")]
+mod win32 {
+    use std::path::Path;
+
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    use super::SourceApp;
+
+    /// Executable name (without `.exe`) and PID of the foreground window's process
+    pub fn foreground_app() -> Option<SourceApp> {
+        unsafe {
+            let window = GetForegroundWindow();
+            let mut pid = 0;
+            GetWindowThreadProcessId(window, Some(&mut pid));
+            if pid == 0 {
+                return None;
+            }
+
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut path = [0u16; 1024];
+            let mut len = path.len() as u32;
+            let queried = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(path.as_mut_ptr()), &mut len);
+            CloseHandle(process).ok();
+            queried.ok()?;
+
+            let path = String::from_utf16_lossy(&path[..len as usize]);
+            let name = Path::new(&path).file_stem()?.to_string_lossy().into_owned();
+            Some(SourceApp { name, pid: Some(pid) })
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    use super::SourceApp;
+
+    /// Name and PID of the frontmost app
+    pub fn frontmost_app() -> Option<SourceApp> {
+        unsafe {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let app: id = msg_send![workspace, frontmostApplication];
+            if app == nil {
+                return None;
+            }
+            let name: id = msg_send![app, localizedName];
+            if name == nil {
+                return None;
+            }
+            let name: *const c_char = msg_send![name, UTF8String];
+            let pid: i32 = msg_send![app, processIdentifier];
+            Some(SourceApp {
+                name: CStr::from_ptr(name).to_string_lossy().into_owned(),
+                pid: u32::try_from(pid).ok(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let keepass = SourceApp { name: "KeePassXC".to_string(), pid: Some(42) };
+        let firefox = SourceApp { name: "firefox".to_string(), pid: None };
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        assert!(is_app_allowed(Some(&keepass), &[], &[]));
+        assert!(!is_app_allowed(Some(&keepass), &[], &names(&["keepassxc"])));
+        assert!(is_app_allowed(Some(&firefox), &[], &names(&["keepassxc"])));
+
+        // An allow list stores nothing else; the deny list still applies to what it allows
+        let allowed = names(&["Firefox ", "KeePassXC"]);
+        assert!(is_app_allowed(Some(&firefox), &allowed, &[]));
+        assert!(!is_app_allowed(Some(&keepass), &allowed, &names(&["KeePassXC"])));
+        assert!(!is_app_allowed(Some(&SourceApp { name: "mstsc".to_string(), pid: None }), &allowed, &[]));
+
+        // Unknown sources can't be told apart
+        assert!(is_app_allowed(None, &allowed, &names(&["KeePassXC"])));
+    }
+}
@@ -1,7 +1,10 @@
-use rusqlite::{Connection, params, Result, OptionalExtension};
+use rusqlite::{Connection, params, Result, OptionalExtension, Row};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use crate::classify::{classify_clip, TextKind};
 use crate::crypto::Crypto;
+use crate::fuzzy::rank_score;
+use crate::query::{FieldMatch, Query, SearchField};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -14,7 +17,7 @@ pub enum ContentType {
 }
 
 impl ContentType {
-    fn to_string(&self) -> &str {
+    pub(crate) fn to_string(&self) -> &str {
         match self {
             ContentType::Text => "text",
             ContentType::Image => "image",
@@ -24,13 +27,51 @@ impl ContentType {
         }
     }
 
+    /// Whether the content of this type is text that can be edited in place
+    pub fn is_editable(&self) -> bool {
+        matches!(self, ContentType::Text | ContentType::Html | ContentType::File)
+    }
+
     fn from_string(s: &str) -> Self {
+        Self::from_name(s).unwrap_or(ContentType::Text)
+    }
+
+    /// Parse a stored or user-typed type name, `None` if unknown
+    pub fn from_name(s: &str) -> Option<Self> {
         match s {
-            "image" => ContentType::Image,
-            "file" => ContentType::File,
-            "html" => ContentType::Html,
-            "rtf" => ContentType::Rtf,
-            _ => ContentType::Text,
+            "text" => Some(ContentType::Text),
+            "image" => Some(ContentType::Image),
+            "file" => Some(ContentType::File),
+            "html" => Some(ContentType::Html),
+            "rtf" => Some(ContentType::Rtf),
+            _ => None,
+        }
+    }
+}
+
+/// The X11 selection a clip was captured from: the clipboard (copy and paste) or
+/// PRIMARY (select and middle-click)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    Primary,
+}
+
+impl Selection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Selection::Clipboard => "clipboard",
+            Selection::Primary => "primary",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clipboard" => Some(Selection::Clipboard),
+            "primary" => Some(Selection::Primary),
+            _ => None,
         }
     }
 }
@@ -45,6 +86,186 @@ pub struct ClipItem {
     pub timestamp: i64,
     pub is_pinned: bool,
     pub pin_order: Option<i32>,
+    pub pin_group_id: Option<String>,
+    pub title: Option<String>,
+    pub note: Option<String>,
+    /// Unix timestamp after which the sweeper deletes the clip
+    pub expires_at: Option<i64>,
+    /// Number of pastes left before the clip is deleted (burn-after-paste)
+    pub pastes_remaining: Option<u32>,
+    /// How many times the clip was copied back from history
+    pub copy_count: u32,
+    pub last_used_at: Option<i64>,
+    /// Lowercase tags, sorted
+    pub tags: Vec<String>,
+    /// Plain-text alternative captured with rich text (HTML, RTF) clips
+    pub plain_text: Option<String>,
+    /// Holds a secret (card number, token, key, password); masked in previews
+    pub sensitive: bool,
+    /// Seconds after a paste from history at which the clipboard is cleared again
+    pub clear_after_secs: Option<u32>,
+    /// App the clip was copied from (WM_CLASS, executable or app name) and its PID
+    pub source_app: Option<String>,
+    pub source_pid: Option<u32>,
+    /// What the text holds (URL, email, code, ...); `None` for images and files
+    pub text_kind: Option<TextKind>,
+    pub selection: Selection,
+}
+
+impl ClipItem {
+    /// Create a new unpinned item with a fresh ID, timestamped now
+    pub fn new(content: Vec<u8>, content_type: ContentType) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            content,
+            content_type,
+            timestamp: chrono::Utc::now().timestamp(),
+            is_pinned: false,
+            pin_order: None,
+            pin_group_id: None,
+            title: None,
+            note: None,
+            expires_at: None,
+            pastes_remaining: None,
+            copy_count: 0,
+            last_used_at: None,
+            tags: Vec::new(),
+            plain_text: None,
+            sensitive: false,
+            clear_after_secs: None,
+            source_app: None,
+            source_pid: None,
+            text_kind: None,
+            selection: Selection::Clipboard,
+        }
+    }
+}
+
+/// Another representation of a clip offered by the source app alongside the primary
+/// content (e.g. the HTML and RTF of a copied document). Restored together with it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipFormat {
+    pub content_type: ContentType,
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// A named group of pinned items, shown as its own submenu in the tray
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinGroup {
+    pub id: String,
+    pub name: String,
+    pub position: i32,
+}
+
+/// Ordering of history, search results and the tray's recent list
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SortMode {
+    #[default]
+    Recent,
+    MostUsed,
+    Frecency,
+    Size,
+    Type,
+    /// Best search match first (match quality, pin state and recency).
+    /// Without a search query this is the same as `Recent`.
+    Relevance,
+}
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Frecency weight by time since last use: (max age in seconds, weight)
+const FRECENCY_BUCKETS: [(i64, u64); 4] = [
+    (4 * DAY_SECS, 100),
+    (14 * DAY_SECS, 70),
+    (31 * DAY_SECS, 50),
+    (90 * DAY_SECS, 30),
+];
+const FRECENCY_OLD_WEIGHT: u64 = 10;
+
+/// Frecency score: use count scaled by how recently the clip was last used.
+/// Clips never copied back count as one use at capture time.
+pub fn frecency_score(copy_count: u32, last_used_at: Option<i64>, timestamp: i64, now: i64) -> u64 {
+    let age = now - last_used_at.unwrap_or(timestamp);
+    let weight = FRECENCY_BUCKETS.iter()
+        .find(|(max_age, _)| age < *max_age)
+        .map(|(_, weight)| *weight)
+        .unwrap_or(FRECENCY_OLD_WEIGHT);
+
+    (copy_count as u64 + 1) * weight
+}
+
+impl SortMode {
+    /// Name stored in the database, same as the serde name
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortMode::Recent => "recent",
+            SortMode::MostUsed => "mostUsed",
+            SortMode::Frecency => "frecency",
+            SortMode::Size => "size",
+            SortMode::Type => "type",
+            SortMode::Relevance => "relevance",
+        }
+    }
+
+    fn from_name(s: &str) -> Self {
+        match s {
+            "mostUsed" => SortMode::MostUsed,
+            "frecency" => SortMode::Frecency,
+            "size" => SortMode::Size,
+            "type" => SortMode::Type,
+            "relevance" => SortMode::Relevance,
+            _ => SortMode::Recent,
+        }
+    }
+
+    /// ORDER BY clause for queries over `CLIP_COLUMNS`
+    fn order_by(&self) -> String {
+        match self {
+            SortMode::Recent | SortMode::Relevance => "timestamp DESC".to_string(),
+            SortMode::MostUsed => "copy_count DESC, timestamp DESC".to_string(),
+            SortMode::Frecency => {
+                // SQL equivalent of frecency_score()
+                const AGE: &str = "(CAST(strftime('%s', 'now') AS INTEGER) - COALESCE(last_used_at, timestamp))";
+                let buckets: String = FRECENCY_BUCKETS.iter()
+                    .map(|(max_age, weight)| format!("WHEN {} < {} THEN {} ", AGE, max_age, weight))
+                    .collect();
+                format!(
+                    "(copy_count + 1) * CASE {}ELSE {} END DESC, timestamp DESC",
+                    buckets, FRECENCY_OLD_WEIGHT
+                )
+            }
+            // Encryption adds a constant overhead, so the stored length orders like the plaintext
+            SortMode::Size => "LENGTH(content) DESC, timestamp DESC".to_string(),
+            SortMode::Type => "content_type ASC, timestamp DESC".to_string(),
+        }
+    }
+}
+
+/// A named query, listed as a smart collection and optionally as a tray submenu
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    /// Query in the search language (see query.rs)
+    pub query: String,
+    pub sort: SortMode,
+    pub show_in_tray: bool,
+    pub created_at: i64,
+}
+
+/// A previous version of an edited clip
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipRevision {
+    pub id: i64,
+    pub clip_id: String,
+    pub content: String,
+    pub created_at: i64,
 }
 
 // Frontend-optimized version: converts images to data URLs for zero-cost rendering
@@ -57,6 +278,21 @@ pub struct FrontendClipItem {
     pub timestamp: i64,
     pub is_pinned: bool,
     pub pin_order: Option<i32>,
+    pub pin_group_id: Option<String>,
+    pub title: Option<String>,
+    pub note: Option<String>,
+    pub expires_at: Option<i64>,
+    pub pastes_remaining: Option<u32>,
+    pub copy_count: u32,
+    pub last_used_at: Option<i64>,
+    pub tags: Vec<String>,
+    pub plain_text: Option<String>,
+    pub sensitive: bool,
+    pub clear_after_secs: Option<u32>,
+    pub source_app: Option<String>,
+    pub source_pid: Option<u32>,
+    pub text_kind: Option<TextKind>,
+    pub selection: Selection,
 }
 
 impl From<ClipItem> for FrontendClipItem {
@@ -81,10 +317,93 @@ impl From<ClipItem> for FrontendClipItem {
             timestamp: item.timestamp,
             is_pinned: item.is_pinned,
             pin_order: item.pin_order,
+            pin_group_id: item.pin_group_id,
+            title: item.title,
+            note: item.note,
+            expires_at: item.expires_at,
+            pastes_remaining: item.pastes_remaining,
+            copy_count: item.copy_count,
+            last_used_at: item.last_used_at,
+            tags: item.tags,
+            plain_text: item.plain_text,
+            sensitive: item.sensitive,
+            clear_after_secs: item.clear_after_secs,
+            source_app: item.source_app,
+            source_pid: item.source_pid,
+            text_kind: item.text_kind,
+            selection: item.selection,
         }
     }
 }
 
+/// A clip matched by a search, with its ranking score and highlighted ranges
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub item: ClipItem,
+    pub score: i64,
+    pub matches: Vec<FieldMatch>,
+    pub matched_fields: Vec<SearchField>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontendSearchResult {
+    pub item: FrontendClipItem,
+    pub score: i64,
+    /// Character ranges to highlight, per field
+    pub matches: Vec<FieldMatch>,
+    /// Fields that matched (content, file name, path, title, note)
+    pub matched_fields: Vec<SearchField>,
+}
+
+impl From<SearchResult> for FrontendSearchResult {
+    fn from(result: SearchResult) -> Self {
+        FrontendSearchResult {
+            item: FrontendClipItem::from(result.item),
+            score: result.score,
+            matches: result.matches,
+            matched_fields: result.matched_fields,
+        }
+    }
+}
+
+/// Columns selected for every clip query, in the order expected by `clip_from_row`.
+/// Must be selected `FROM clips` (the tag subquery refers to `clips.id`).
+const CLIP_COLUMNS: &str = "id, content, content_type, timestamp, is_pinned, pin_order, pin_group_id, title, note, expires_at, pastes_remaining,
+    copy_count, last_used_at, plain_text, sensitive, clear_after_secs, source_app, source_pid, text_kind,
+    selection, (SELECT GROUP_CONCAT(tag, ',') FROM (SELECT tag FROM clip_tags WHERE clip_tags.clip_id = clips.id ORDER BY tag)) AS tags";
+
+/// Number of revisions kept per clip; older ones are dropped on edit
+const MAX_REVISIONS_PER_CLIP: usize = 20;
+
+/// SHA-256 of the plaintext content, used for deduplication
+fn content_hash(content: &[u8]) -> String {
+    use sha2::{Sha256, Digest};
+
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Add a column to an existing table if an older database doesn't have it yet
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |row| {
+            let name: String = row.get(1)?;
+            Ok(name)
+        })?
+        .filter_map(Result::ok)
+        .any(|name| name == column);
+
+    if !exists {
+        log::info!("📦 Migrating database: adding {}.{} column", table, column);
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+
+    Ok(())
+}
+
 pub struct ClipStorage {
     conn: Connection,
     crypto: Option<Arc<Crypto>>,
@@ -94,6 +413,9 @@ impl ClipStorage {
     pub fn new(db_path: &str, crypto: Option<Arc<Crypto>>) -> Result<Self> {
         let conn = Connection::open(db_path)?;
 
+        // Needed for ON DELETE CASCADE on child tables (clip_revisions)
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+
         // Create main table
         conn.execute(
             "CREATE TABLE IF NOT EXISTS clips (
@@ -107,21 +429,88 @@ impl ClipStorage {
             [],
         )?;
 
-        // Migration: Add content_hash column if it doesn't exist
-        // Check if column exists by querying pragma
-        let has_content_hash: bool = conn
-            .prepare("PRAGMA table_info(clips)")?
-            .query_map([], |row| {
-                let name: String = row.get(1)?;
-                Ok(name)
-            })?
-            .filter_map(Result::ok)
-            .any(|name| name == "content_hash");
-
-        if !has_content_hash {
-            log::info!("📦 Migrating database: adding content_hash column");
-            conn.execute("ALTER TABLE clips ADD COLUMN content_hash TEXT", [])?;
-        }
+        // Migrations for columns added after the initial schema
+        add_column_if_missing(&conn, "clips", "content_hash", "TEXT")?;
+        add_column_if_missing(&conn, "clips", "pin_group_id", "TEXT")?;
+        // Title and note are user-written text, encrypted like content
+        add_column_if_missing(&conn, "clips", "title", "BLOB")?;
+        add_column_if_missing(&conn, "clips", "note", "BLOB")?;
+        add_column_if_missing(&conn, "clips", "expires_at", "INTEGER")?;
+        add_column_if_missing(&conn, "clips", "pastes_remaining", "INTEGER")?;
+        add_column_if_missing(&conn, "clips", "copy_count", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "clips", "last_used_at", "INTEGER")?;
+        add_column_if_missing(&conn, "clips", "plain_text", "BLOB")?;
+        add_column_if_missing(&conn, "clips", "sensitive", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "clips", "clear_after_secs", "INTEGER")?;
+        add_column_if_missing(&conn, "clips", "source_app", "TEXT")?;
+        add_column_if_missing(&conn, "clips", "source_pid", "INTEGER")?;
+        add_column_if_missing(&conn, "clips", "text_kind", "TEXT")?;
+        add_column_if_missing(&conn, "clips", "selection", "TEXT NOT NULL DEFAULT 'clipboard'")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS pin_groups (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                position INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Previous versions of edited clips, encrypted like clips.content
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS clip_revisions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                clip_id TEXT NOT NULL REFERENCES clips(id) ON DELETE CASCADE,
+                content BLOB NOT NULL,
+                content_hash TEXT,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_revisions_clip ON clip_revisions(clip_id, created_at DESC)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS saved_searches (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                query TEXT NOT NULL,
+                sort TEXT NOT NULL,
+                show_in_tray INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Tags are matched by `tag:` search filters in SQL, so they are stored in plaintext
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS clip_tags (
+                clip_id TEXT NOT NULL REFERENCES clips(id) ON DELETE CASCADE,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (clip_id, tag)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_clip_tags_tag ON clip_tags(tag)",
+            [],
+        )?;
+
+        // Alternative representations of multi-format clips, encrypted like clips.content.
+        // Rowid order is the source app's order of preference.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS clip_formats (
+                clip_id TEXT NOT NULL REFERENCES clips(id) ON DELETE CASCADE,
+                content_type TEXT NOT NULL,
+                data BLOB NOT NULL,
+                PRIMARY KEY (clip_id, content_type)
+            )",
+            [],
+        )?;
 
         // Create index for fast queries
         conn.execute(
@@ -134,6 +523,11 @@ impl ClipStorage {
             [],
         )?;
 
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_expires_at ON clips(expires_at) WHERE expires_at IS NOT NULL",
+            [],
+        )?;
+
         // Create index for content hash to speed up duplicate checks
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_content_hash ON clips(content_hash, content_type)",
@@ -144,12 +538,14 @@ impl ClipStorage {
     }
 
     pub fn insert(&self, item: &ClipItem, max_history_items: usize) -> Result<Option<String>> {
-        use sha2::{Sha256, Digest};
+        self.insert_with_formats(item, &[], max_history_items)
+    }
 
+    /// Insert a clip with the alternative formats captured alongside it.
+    /// Returns the id of the existing clip if the content was a duplicate.
+    pub fn insert_with_formats(&self, item: &ClipItem, formats: &[ClipFormat], max_history_items: usize) -> Result<Option<String>> {
         // Calculate content hash for deduplication
-        let mut hasher = Sha256::new();
-        hasher.update(&item.content);
-        let content_hash = format!("{:x}", hasher.finalize());
+        let content_hash = content_hash(&item.content);
 
         // Check if content already exists in recent items (last 100)
         let existing_id: Option<String> = self.conn.query_row(
@@ -166,24 +562,59 @@ impl ClipStorage {
             
             // Update timestamp of existing item using the shared method
             self.update_timestamp(&id, item.timestamp)?;
+
+            // Expiry set by capture rules applies to the re-captured item as well
+            if item.expires_at.is_some() || item.pastes_remaining.is_some() || item.clear_after_secs.is_some() {
+                self.conn.execute(
+                    "UPDATE clips
+                     SET expires_at = COALESCE(?1, expires_at),
+                         pastes_remaining = COALESCE(?2, pastes_remaining),
+                         clear_after_secs = COALESCE(?3, clear_after_secs)
+                     WHERE id = ?4",
+                    params![item.expires_at, item.pastes_remaining, item.clear_after_secs, id],
+                )?;
+            }
+
+            // A secret stays flagged however often it is copied
+            if item.sensitive {
+                self.conn.execute("UPDATE clips SET sensitive = 1 WHERE id = ?1", params![id])?;
+            }
+
+            // Fills in the label of clips captured before text was classified
+            if let Some(kind) = item.text_kind {
+                self.conn.execute("UPDATE clips SET text_kind = ?1 WHERE id = ?2", params![kind.as_str(), id])?;
+            }
+
+            // The clip now comes from the app that copied it last
+            if item.source_app.is_some() {
+                self.conn.execute(
+                    "UPDATE clips SET source_app = ?1, source_pid = ?2 WHERE id = ?3",
+                    params![item.source_app, item.source_pid, id],
+                )?;
+            }
+
+            // Copying text that was only selected before makes it a clipboard clip
+            if item.selection == Selection::Clipboard {
+                self.conn.execute("UPDATE clips SET selection = 'clipboard' WHERE id = ?1", params![id])?;
+            }
+
+            // The latest copy decides which formats are restored
+            self.replace_formats(&id, formats)?;
             
             return Ok(Some(id));
         }
 
-        // Encrypt content if crypto is available
-        let content_to_store = if let Some(crypto) = &self.crypto {
-            crypto.encrypt(&item.content)
-                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    e
-                ))))?
-        } else {
-            item.content.clone()
-        };
+        let content_to_store = self.encrypt_content(&item.content)?;
+        // Not trimmed like title and note: it is copied back as-is
+        let plain_text_to_store = item.plain_text.as_deref()
+            .map(|text| self.encrypt_content(text.as_bytes()))
+            .transpose()?;
 
         self.conn.execute(
-            "INSERT INTO clips (id, content, content_hash, content_type, timestamp, is_pinned, pin_order)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO clips (id, content, content_hash, content_type, timestamp, is_pinned, pin_order, pin_group_id,
+                                expires_at, pastes_remaining, plain_text, sensitive, clear_after_secs, source_app, source_pid,
+                                text_kind, selection)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
             params![
                 item.id,
                 content_to_store,
@@ -192,8 +623,19 @@ impl ClipStorage {
                 item.timestamp,
                 item.is_pinned as i32,
                 item.pin_order,
+                item.pin_group_id,
+                item.expires_at,
+                item.pastes_remaining,
+                plain_text_to_store,
+                item.sensitive as i32,
+                item.clear_after_secs,
+                item.source_app,
+                item.source_pid,
+                item.text_kind.map(|kind| kind.as_str()),
+                item.selection.as_str(),
             ],
         )?;
+        self.replace_formats(&item.id, formats)?;
 
         // Auto-cleanup old items (keep last max_history_items)
         self.conn.execute(
@@ -210,6 +652,48 @@ impl ClipStorage {
         Ok(None)
     }
 
+    fn replace_formats(&self, id: &str, formats: &[ClipFormat]) -> Result<()> {
+        self.conn.execute("DELETE FROM clip_formats WHERE clip_id = ?1", params![id])?;
+        for format in formats {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO clip_formats (clip_id, content_type, data) VALUES (?1, ?2, ?3)",
+                params![id, format.content_type.to_string(), self.encrypt_content(&format.data)?],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Alternative formats of a clip, in the source app's order of preference.
+    /// Formats that fail to decrypt are skipped.
+    pub fn get_formats(&self, id: &str) -> Result<Vec<ClipFormat>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT content_type, data FROM clip_formats WHERE clip_id = ?1 ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map(params![id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })?;
+
+        let mut formats = Vec::new();
+        for row in rows {
+            let (content_type, data) = row?;
+            match self.decrypt_content(data) {
+                Ok(data) => formats.push(ClipFormat { content_type: ContentType::from_string(&content_type), data }),
+                Err(e) => log::warn!("⚠️ Failed to decrypt {} format of {}: {:?}", content_type, id, e),
+            }
+        }
+        Ok(formats)
+    }
+
+    // Helper method to encrypt content if crypto is available
+    fn encrypt_content(&self, content: &[u8]) -> Result<Vec<u8>> {
+        if let Some(crypto) = &self.crypto {
+            crypto.encrypt(content)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(std::io::Error::other(e))))
+        } else {
+            Ok(content.to_vec())
+        }
+    }
+
     // Helper method to decrypt content
     fn decrypt_content(&self, encrypted: Vec<u8>) -> Result<Vec<u8>> {
         if let Some(crypto) = &self.crypto {
@@ -217,55 +701,75 @@ impl ClipStorage {
                 .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
                     0,
                     rusqlite::types::Type::Blob,
-                    Box::new(std::io::Error::new(std::io::ErrorKind::Other, e))
+                    Box::new(std::io::Error::other(e))
                 ))
         } else {
             Ok(encrypted)
         }
     }
 
-    pub fn get_recent(&self, limit: usize) -> Result<Vec<ClipItem>> {
-        // Query to get all pinned items plus the most recent N non-pinned items
-        // This ensures pinned items are always visible regardless of timestamp
-        let mut stmt = self.conn.prepare(
-            "SELECT id, content, content_type, timestamp, is_pinned, pin_order
-             FROM clips
-             WHERE is_pinned = 1
-             UNION ALL
-             SELECT id, content, content_type, timestamp, is_pinned, pin_order
-             FROM (
-                 SELECT id, content, content_type, timestamp, is_pinned, pin_order
-                 FROM clips
-                 WHERE is_pinned = 0
-                 ORDER BY timestamp DESC
-                 LIMIT ?1
-             )
-             ORDER BY timestamp DESC"
-        )?;
-
-        let items = stmt.query_map([limit], |row| {
-            let encrypted_content: Vec<u8> = row.get(1)?;
-            let content = match self.decrypt_content(encrypted_content.clone()) {
-                Ok(c) => c,
-                Err(e) => {
-                    // 解密失败，记录错误并跳过此项
-                    let id: String = row.get(0).unwrap_or_else(|_| "unknown".to_string());
-                    log::warn!("⚠️ Failed to decrypt item {}: {:?}. Skipping.", id, e);
-                    // 返回空内容以避免整个查询失败
-                    Vec::new()
-                }
-            };
-
-            // 如果内容为空（解密失败），我们仍然返回item但标记为无效
-            Ok(ClipItem {
-                id: row.get(0)?,
-                content,
-                content_type: ContentType::from_string(&row.get::<_, String>(2)?),
-                timestamp: row.get(3)?,
-                is_pinned: row.get::<_, i32>(4)? != 0,
-                pin_order: row.get(5)?,
-            })
-        })?;
+    /// Decrypt an optional text column (title, note, plain text). Unreadable values are dropped.
+    fn decrypt_text(&self, encrypted: Option<Vec<u8>>) -> Option<String> {
+        let decrypted = self.decrypt_content(encrypted?)
+            .map_err(|e| log::warn!("⚠️ Failed to decrypt text field: {:?}", e))
+            .ok()?;
+        String::from_utf8(decrypted).ok()
+    }
+
+    /// Encrypt an optional text column, storing blank text as NULL
+    fn encrypt_text(&self, text: Option<&str>) -> Result<Option<Vec<u8>>> {
+        match text.map(str::trim).filter(|t| !t.is_empty()) {
+            Some(t) => Ok(Some(self.encrypt_content(t.as_bytes())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Build a ClipItem from a row selected with `CLIP_COLUMNS`.
+    /// Items that fail to decrypt come back with empty content so callers can skip them.
+    fn clip_from_row(&self, row: &Row) -> Result<ClipItem> {
+        let id: String = row.get(0)?;
+        let encrypted_content: Vec<u8> = row.get(1)?;
+        let content = match self.decrypt_content(encrypted_content) {
+            Ok(c) => c,
+            Err(e) => {
+                // 解密失败，记录错误并跳过此项
+                log::warn!("⚠️ Failed to decrypt item {}: {:?}. Skipping.", id, e);
+                Vec::new()
+            }
+        };
+
+        Ok(ClipItem {
+            id,
+            content,
+            content_type: ContentType::from_string(&row.get::<_, String>(2)?),
+            timestamp: row.get(3)?,
+            is_pinned: row.get::<_, i32>(4)? != 0,
+            pin_order: row.get(5)?,
+            pin_group_id: row.get(6)?,
+            title: self.decrypt_text(row.get(7)?),
+            note: self.decrypt_text(row.get(8)?),
+            expires_at: row.get(9)?,
+            pastes_remaining: row.get(10)?,
+            copy_count: row.get(11)?,
+            last_used_at: row.get(12)?,
+            plain_text: self.decrypt_text(row.get(13)?),
+            sensitive: row.get::<_, i32>(14)? != 0,
+            clear_after_secs: row.get(15)?,
+            source_app: row.get(16)?,
+            source_pid: row.get(17)?,
+            text_kind: row.get::<_, Option<String>>(18)?.as_deref().and_then(TextKind::from_name),
+            selection: Selection::from_name(&row.get::<_, String>(19)?).unwrap_or_default(),
+            tags: row
+                .get::<_, Option<String>>(20)?
+                .map(|tags| tags.split(',').map(str::to_string).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Run a clip query and drop items whose content could not be decrypted
+    fn query_clips<P: rusqlite::Params>(&self, sql: &str, params: P) -> Result<Vec<ClipItem>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let items = stmt.query_map(params, |row| self.clip_from_row(row))?;
 
         // 过滤掉解密失败的项目（内容为空）
         items.filter_map(|item| {
@@ -277,60 +781,108 @@ impl ClipStorage {
         }).collect()
     }
 
-    pub fn search(&self, query: &str) -> Result<Vec<ClipItem>> {
-        // FTS5 需要对特殊字符进行转义，或者使用简单的 LIKE 查询
-        // 对于用户输入，我们使用更简单的方法：直接搜索文本内容
-        log::info!("🔍 Searching for: {}", query);
+    pub fn get_recent(&self, limit: usize, sort: SortMode) -> Result<Vec<ClipItem>> {
+        // Query to get all pinned items plus the top N non-pinned items in the chosen order
+        // This ensures pinned items are always visible regardless of timestamp
+        self.query_clips(
+            &format!(
+                "SELECT * FROM (
+                     SELECT {cols}
+                     FROM clips
+                     WHERE is_pinned = 1
+                     UNION ALL
+                     SELECT *
+                     FROM (
+                         SELECT {cols}
+                         FROM clips
+                         WHERE is_pinned = 0
+                         ORDER BY {order}
+                         LIMIT ?1
+                     )
+                 )
+                 ORDER BY {order}",
+                cols = CLIP_COLUMNS,
+                order = sort.order_by()
+            ),
+            [limit],
+        )
+    }
 
-        let mut stmt = self.conn.prepare(
-            "SELECT id, content, content_type, timestamp, is_pinned, pin_order
-             FROM clips
-             WHERE content_type = 'text'
-             ORDER BY timestamp DESC
-             LIMIT 1000"
-        )?;
+    pub fn search(&self, query: &Query, sort: SortMode, now: i64) -> Result<Vec<SearchResult>> {
+        // Content is encrypted, so SQL only narrows by the plaintext columns (type, pinned,
+        // tags, dates); text, regex and size filters are checked in memory
+        let (filter, params) = query.sql_filter();
+        log::info!("🔍 Searching with filter: {}", filter);
 
-        let items = stmt.query_map([], |row| {
-            let encrypted_content: Vec<u8> = row.get(1)?;
-            let content = match self.decrypt_content(encrypted_content.clone()) {
-                Ok(c) => c,
-                Err(e) => {
-                    let id: String = row.get(0).unwrap_or_else(|_| "unknown".to_string());
-                    log::warn!("⚠️ Failed to decrypt search result {}: {:?}. Skipping.", id, e);
-                    Vec::new()
-                }
-            };
+        let items = self.query_clips(
+            &format!(
+                "SELECT {}
+                 FROM clips
+                 WHERE {}
+                 ORDER BY {}
+                 LIMIT 1000",
+                CLIP_COLUMNS,
+                filter,
+                sort.order_by()
+            ),
+            rusqlite::params_from_iter(params),
+        )?;
 
-            Ok(ClipItem {
-                id: row.get(0)?,
-                content,
-                content_type: ContentType::from_string(&row.get::<_, String>(2)?),
-                timestamp: row.get(3)?,
-                is_pinned: row.get::<_, i32>(4)? != 0,
-                pin_order: row.get(5)?,
+        let mut results: Vec<SearchResult> = items
+            .into_iter()
+            .filter_map(|item| {
+                let hit = query.evaluate(&item)?;
+                Some(SearchResult {
+                    score: rank_score(hit.score, item.is_pinned, item.timestamp, now),
+                    matched_fields: hit.fields(),
+                    matches: hit.matches,
+                    item,
+                })
             })
-        })?;
+            .collect();
 
-        // 在内存中过滤搜索结果
-        let query_lower = query.to_lowercase();
-        items.filter_map(|item| {
-            match item {
-                Ok(clip_item) if !clip_item.content.is_empty() => {
-                    // 解码内容并检查是否包含查询字符串
-                    if let Ok(text) = String::from_utf8(clip_item.content.clone()) {
-                        if text.to_lowercase().contains(&query_lower) {
-                            Some(Ok(clip_item))
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                }
-                Ok(_) => None,
-                Err(e) => Some(Err(e)),
-            }
-        }).collect()
+        if sort == SortMode::Relevance {
+            // Stable sort: equal scores stay newest first
+            results.sort_by_key(|result| std::cmp::Reverse(result.score));
+        }
+
+        Ok(results)
+    }
+
+    /// Replace the tags of a clip. Tags are trimmed and lowercased; commas are not allowed
+    /// since they separate tags in query results.
+    pub fn set_tags(&self, id: &str, tags: &[String]) -> Result<Option<ClipItem>> {
+        let mut tags: Vec<String> = tags
+            .iter()
+            .map(|tag| tag.trim().to_lowercase().replace(',', ""))
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+
+        let tx = self.conn.unchecked_transaction()?;
+        let exists = tx
+            .query_row("SELECT 1 FROM clips WHERE id = ?1", params![id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !exists {
+            return Ok(None);
+        }
+
+        tx.execute("DELETE FROM clip_tags WHERE clip_id = ?1", params![id])?;
+        for tag in &tags {
+            tx.execute("INSERT INTO clip_tags (clip_id, tag) VALUES (?1, ?2)", params![id, tag])?;
+        }
+        tx.commit()?;
+
+        self.get_by_id(id)
+    }
+
+    /// All tags in use, alphabetically
+    pub fn get_all_tags(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT tag FROM clip_tags ORDER BY tag")?;
+        let tags = stmt.query_map([], |row| row.get(0))?;
+        tags.collect()
     }
 
     pub fn update_pin(&self, id: &str, is_pinned: bool) -> Result<()> {
@@ -347,14 +899,206 @@ impl ClipStorage {
             None
         };
 
+        // Unpinning also removes the item from its pin group
         self.conn.execute(
-            "UPDATE clips SET is_pinned = ?1, pin_order = ?2 WHERE id = ?3",
+            "UPDATE clips
+             SET is_pinned = ?1, pin_order = ?2,
+                 pin_group_id = CASE WHEN ?1 = 1 THEN pin_group_id ELSE NULL END
+             WHERE id = ?3",
             params![is_pinned as i32, pin_order, id],
         )?;
 
         Ok(())
     }
 
+    /// Move a pinned item to `new_index` (0-based) and renumber all pins in one transaction
+    pub fn move_pin(&self, id: &str, new_index: usize) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let mut pinned_ids: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM clips WHERE is_pinned = 1 ORDER BY pin_order ASC, timestamp DESC"
+            )?;
+            let ids = stmt.query_map([], |row| row.get(0))?;
+            ids.collect::<Result<_>>()?
+        };
+
+        let current_index = pinned_ids.iter()
+            .position(|pinned_id| pinned_id == id)
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+
+        let moved = pinned_ids.remove(current_index);
+        let new_index = new_index.min(pinned_ids.len());
+        pinned_ids.insert(new_index, moved);
+
+        for (index, pinned_id) in pinned_ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE clips SET pin_order = ?1 WHERE id = ?2",
+                params![index as i32 + 1, pinned_id],
+            )?;
+        }
+
+        tx.commit()?;
+        log::debug!("📌 Moved pin {} to position {}", id, new_index);
+        Ok(())
+    }
+
+    /// Insert a saved search or update the one with the same id (keeping its creation time)
+    pub fn save_search(&self, search: &SavedSearch) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO saved_searches (id, name, query, sort, show_in_tray, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                query = excluded.query,
+                sort = excluded.sort,
+                show_in_tray = excluded.show_in_tray",
+            params![
+                search.id,
+                search.name,
+                search.query,
+                search.sort.as_str(),
+                search.show_in_tray as i32,
+                search.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn query_saved_searches<P: rusqlite::Params>(&self, sql: &str, params: P) -> Result<Vec<SavedSearch>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let searches = stmt.query_map(params, |row| {
+            Ok(SavedSearch {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                query: row.get(2)?,
+                sort: SortMode::from_name(&row.get::<_, String>(3)?),
+                show_in_tray: row.get::<_, i32>(4)? != 0,
+                created_at: row.get(5)?,
+            })
+        })?;
+        searches.collect()
+    }
+
+    pub fn get_saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        self.query_saved_searches(
+            "SELECT id, name, query, sort, show_in_tray, created_at FROM saved_searches ORDER BY created_at ASC, name ASC",
+            [],
+        )
+    }
+
+    pub fn get_saved_search(&self, id: &str) -> Result<Option<SavedSearch>> {
+        Ok(self.query_saved_searches(
+            "SELECT id, name, query, sort, show_in_tray, created_at FROM saved_searches WHERE id = ?1",
+            params![id],
+        )?.into_iter().next())
+    }
+
+    pub fn delete_saved_search(&self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM saved_searches WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// IDs of the saved searches that a stored clip matches. Saved queries that no
+    /// longer parse are skipped.
+    pub fn saved_searches_matching(&self, clip_id: &str) -> Result<Vec<String>> {
+        let Some(item) = self.get_by_id(clip_id)? else {
+            return Ok(Vec::new());
+        };
+
+        Ok(self.get_saved_searches()?
+            .into_iter()
+            .filter(|search| Query::parse(&search.query).is_ok_and(|query| query.matches(&item)))
+            .map(|search| search.id)
+            .collect())
+    }
+
+    pub fn get_pin_groups(&self) -> Result<Vec<PinGroup>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, position FROM pin_groups ORDER BY position ASC"
+        )?;
+
+        let groups = stmt.query_map([], |row| {
+            Ok(PinGroup {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                position: row.get(2)?,
+            })
+        })?;
+
+        groups.collect()
+    }
+
+    pub fn create_pin_group(&self, name: &str) -> Result<PinGroup> {
+        let max_position: Option<i32> = self.conn.query_row(
+            "SELECT MAX(position) FROM pin_groups",
+            [],
+            |row| row.get(0),
+        )?;
+
+        let group = PinGroup {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            position: max_position.unwrap_or(0) + 1,
+        };
+
+        self.conn.execute(
+            "INSERT INTO pin_groups (id, name, position) VALUES (?1, ?2, ?3)",
+            params![group.id, group.name, group.position],
+        )?;
+
+        Ok(group)
+    }
+
+    /// Fails with QueryReturnedNoRows if the group doesn't exist
+    pub fn rename_pin_group(&self, id: &str, name: &str) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE pin_groups SET name = ?1 WHERE id = ?2",
+            params![name, id],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    /// Delete a pin group. Its items stay pinned but become ungrouped.
+    pub fn delete_pin_group(&self, id: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("UPDATE clips SET pin_group_id = NULL WHERE pin_group_id = ?1", params![id])?;
+        tx.execute("DELETE FROM pin_groups WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+
+    /// Assign an item to a pin group (or ungroup it with `None`). Unpinned items get pinned.
+    pub fn assign_pin_group(&self, id: &str, group_id: Option<&str>) -> Result<()> {
+        if let Some(group_id) = group_id {
+            // Fails with QueryReturnedNoRows if the group doesn't exist
+            self.conn.query_row(
+                "SELECT id FROM pin_groups WHERE id = ?1",
+                params![group_id],
+                |row| row.get::<_, String>(0),
+            )?;
+        }
+
+        let is_pinned: bool = self.conn.query_row(
+            "SELECT is_pinned FROM clips WHERE id = ?1",
+            params![id],
+            |row| Ok(row.get::<_, i32>(0)? != 0),
+        )?;
+
+        if !is_pinned {
+            self.update_pin(id, true)?;
+        }
+
+        self.conn.execute(
+            "UPDATE clips SET pin_group_id = ?1 WHERE id = ?2",
+            params![group_id, id],
+        )?;
+
+        Ok(())
+    }
+
     pub fn delete(&self, id: &str) -> Result<()> {
         self.conn.execute("DELETE FROM clips WHERE id = ?1", params![id])?;
         Ok(())
@@ -374,31 +1118,11 @@ impl ClipStorage {
 
     /// Get a single clip item by ID (efficient single-row lookup)
     pub fn get_by_id(&self, id: &str) -> Result<Option<ClipItem>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, content, content_type, timestamp, is_pinned, pin_order
-             FROM clips
-             WHERE id = ?1"
-        )?;
-
-        let item = stmt.query_row([id], |row| {
-            let encrypted_content: Vec<u8> = row.get(1)?;
-            let content = match self.decrypt_content(encrypted_content.clone()) {
-                Ok(c) => c,
-                Err(e) => {
-                    log::warn!("⚠️ Failed to decrypt item {}: {:?}", id, e);
-                    Vec::new()
-                }
-            };
-
-            Ok(ClipItem {
-                id: row.get(0)?,
-                content,
-                content_type: ContentType::from_string(&row.get::<_, String>(2)?),
-                timestamp: row.get(3)?,
-                is_pinned: row.get::<_, i32>(4)? != 0,
-                pin_order: row.get(5)?,
-            })
-        }).optional()?;
+        let item = self.conn.query_row(
+            &format!("SELECT {} FROM clips WHERE id = ?1", CLIP_COLUMNS),
+            [id],
+            |row| self.clip_from_row(row),
+        ).optional()?;
 
         // Filter out items with empty content (decryption failed)
         Ok(item.filter(|i| !i.content.is_empty()))
@@ -414,41 +1138,680 @@ impl ClipStorage {
         Ok(())
     }
 
-    pub fn get_pinned(&self) -> Result<Vec<ClipItem>> {
+    /// Replace the content of a clip, keeping the previous version as a revision.
+    /// Returns the updated item, or None if the clip doesn't exist.
+    pub fn update_clip_content(&self, id: &str, content: &[u8], now: i64) -> Result<Option<ClipItem>> {
+        let current_hash: Option<Option<String>> = self.conn.query_row(
+            "SELECT content_hash FROM clips WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        ).optional()?;
+
+        let Some(current_hash) = current_hash else {
+            return Ok(None);
+        };
+
+        let new_hash = content_hash(content);
+        if current_hash.as_deref() == Some(new_hash.as_str()) {
+            log::debug!("✏️ Content of {} unchanged, skipping revision", id);
+            return self.get_by_id(id);
+        }
+
+        let encrypted = self.encrypt_content(content)?;
+        let tx = self.conn.unchecked_transaction()?;
+
+        // The stored blob is already encrypted, so it can be copied as-is
+        tx.execute(
+            "INSERT INTO clip_revisions (clip_id, content, content_hash, created_at)
+             SELECT id, content, content_hash, ?2 FROM clips WHERE id = ?1",
+            params![id, now],
+        )?;
+
+        // Re-hash so duplicate detection matches the edited content. Alternatives
+        // captured with the old content (plain text, other formats) no longer match it.
+        tx.execute(
+            "UPDATE clips SET content = ?1, content_hash = ?2, plain_text = NULL WHERE id = ?3",
+            params![encrypted, new_hash, id],
+        )?;
+        tx.execute("DELETE FROM clip_formats WHERE clip_id = ?1", params![id])?;
+
+        tx.execute(
+            "DELETE FROM clip_revisions
+             WHERE clip_id = ?1 AND id NOT IN (
+                SELECT id FROM clip_revisions
+                WHERE clip_id = ?1
+                ORDER BY created_at DESC, id DESC
+                LIMIT ?2
+             )",
+            params![id, MAX_REVISIONS_PER_CLIP],
+        )?;
+
+        tx.commit()?;
+        log::info!("✏️ Updated content of clip {}", id);
+
+        self.get_by_id(id)
+    }
+
+    /// Set the title and note of a clip. Blank values clear the field.
+    pub fn update_clip_details(&self, id: &str, title: Option<&str>, note: Option<&str>) -> Result<Option<ClipItem>> {
+        let title = self.encrypt_text(title)?;
+        let note = self.encrypt_text(note)?;
+
+        self.conn.execute(
+            "UPDATE clips SET title = ?1, note = ?2 WHERE id = ?3",
+            params![title, note, id],
+        )?;
+
+        self.get_by_id(id)
+    }
+
+    /// Count a copy-back from history for usage-based sorting
+    pub fn record_use(&self, id: &str, now: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clips SET copy_count = copy_count + 1, last_used_at = ?1 WHERE id = ?2",
+            params![now, id],
+        )?;
+        Ok(())
+    }
+
+    /// Flag a clip as holding a secret, or clear the flag
+    pub fn set_sensitive(&self, id: &str, sensitive: bool) -> Result<Option<ClipItem>> {
+        self.conn.execute(
+            "UPDATE clips SET sensitive = ?1 WHERE id = ?2",
+            params![sensitive as i32, id],
+        )?;
+        self.get_by_id(id)
+    }
+
+    /// Set or remove the delay after which a paste of the clip is cleared from the clipboard
+    pub fn set_clear_after(&self, id: &str, clear_after_secs: Option<u32>) -> Result<Option<ClipItem>> {
+        self.conn.execute(
+            "UPDATE clips SET clear_after_secs = ?1 WHERE id = ?2",
+            params![clear_after_secs, id],
+        )?;
+        self.get_by_id(id)
+    }
+
+    /// Set or remove the label of what a clip's text holds
+    pub fn set_text_kind(&self, id: &str, text_kind: Option<TextKind>) -> Result<Option<ClipItem>> {
+        self.conn.execute(
+            "UPDATE clips SET text_kind = ?1 WHERE id = ?2",
+            params![text_kind.map(|kind| kind.as_str()), id],
+        )?;
+        self.get_by_id(id)
+    }
+
+    /// Label the text clips stored before text was classified. Returns how many were labelled.
+    pub fn classify_unlabelled(&self) -> Result<usize> {
+        let items = self.query_clips(
+            &format!(
+                "SELECT {} FROM clips WHERE text_kind IS NULL AND content_type IN ('text', 'html', 'rtf')",
+                CLIP_COLUMNS
+            ),
+            [],
+        )?;
+        for item in &items {
+            self.set_text_kind(&item.id, classify_clip(item))?;
+        }
+        Ok(items.len())
+    }
+
+    /// Set or clear the expiry time of a clip
+    pub fn set_expiry(&self, id: &str, expires_at: Option<i64>) -> Result<Option<ClipItem>> {
+        self.conn.execute(
+            "UPDATE clips SET expires_at = ?1 WHERE id = ?2",
+            params![expires_at, id],
+        )?;
+        self.get_by_id(id)
+    }
+
+    /// Set or clear the number of pastes after which a clip is deleted
+    pub fn set_paste_limit(&self, id: &str, max_pastes: Option<u32>) -> Result<Option<ClipItem>> {
+        self.conn.execute(
+            "UPDATE clips SET pastes_remaining = ?1 WHERE id = ?2",
+            params![max_pastes, id],
+        )?;
+        self.get_by_id(id)
+    }
+
+    /// Count a paste of a burn-after-paste clip. Returns true if the clip used up
+    /// its last paste and was deleted.
+    pub fn record_paste(&self, id: &str) -> Result<bool> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        tx.execute(
+            "UPDATE clips SET pastes_remaining = pastes_remaining - 1
+             WHERE id = ?1 AND pastes_remaining IS NOT NULL",
+            params![id],
+        )?;
+        let burned = tx.execute(
+            "DELETE FROM clips WHERE id = ?1 AND pastes_remaining <= 0",
+            params![id],
+        )? > 0;
+
+        tx.commit()?;
+        if burned {
+            log::info!("🔥 Clip {} deleted after its last paste", id);
+        }
+        Ok(burned)
+    }
+
+    /// Delete clips whose expiry time has passed. Returns the deleted IDs.
+    pub fn delete_expired(&self, now: i64) -> Result<Vec<String>> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let ids: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM clips WHERE expires_at IS NOT NULL AND expires_at <= ?1"
+            )?;
+            let ids = stmt.query_map([now], |row| row.get(0))?;
+            ids.collect::<Result<_>>()?
+        };
+
+        tx.execute(
+            "DELETE FROM clips WHERE expires_at IS NOT NULL AND expires_at <= ?1",
+            params![now],
+        )?;
+        tx.commit()?;
+
+        if !ids.is_empty() {
+            log::info!("⏰ Deleted {} expired clips", ids.len());
+        }
+        Ok(ids)
+    }
+
+    /// List previous versions of a clip, newest first
+    pub fn list_revisions(&self, clip_id: &str) -> Result<Vec<ClipRevision>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, content, content_type, timestamp, is_pinned, pin_order
-             FROM clips
-             WHERE is_pinned = 1
-             ORDER BY pin_order ASC"
-        )?;
-
-        let items = stmt.query_map([], |row| {
-            let encrypted_content: Vec<u8> = row.get(1)?;
-            let content = match self.decrypt_content(encrypted_content.clone()) {
-                Ok(c) => c,
-                Err(e) => {
-                    let id: String = row.get(0).unwrap_or_else(|_| "unknown".to_string());
-                    log::warn!("⚠️ Failed to decrypt pinned item {}: {:?}. Skipping.", id, e);
-                    Vec::new()
-                }
-            };
-
-            Ok(ClipItem {
+            "SELECT id, clip_id, content, created_at
+             FROM clip_revisions
+             WHERE clip_id = ?1
+             ORDER BY created_at DESC, id DESC"
+        )?;
+
+        let revisions = stmt.query_map([clip_id], |row| {
+            let content = self.decrypt_content(row.get(2)?)?;
+            Ok(ClipRevision {
                 id: row.get(0)?,
-                content,
-                content_type: ContentType::from_string(&row.get::<_, String>(2)?),
-                timestamp: row.get(3)?,
-                is_pinned: row.get::<_, i32>(4)? != 0,
-                pin_order: row.get(5)?,
+                clip_id: row.get(1)?,
+                content: String::from_utf8_lossy(&content).to_string(),
+                created_at: row.get(3)?,
             })
         })?;
 
-        items.filter_map(|item| {
-            match item {
-                Ok(clip_item) if !clip_item.content.is_empty() => Some(Ok(clip_item)),
-                Ok(_) => None,
-                Err(e) => Some(Err(e)),
+        revisions.collect()
+    }
+
+    /// Restore a revision. The content being replaced is kept as a new revision,
+    /// so a revert can itself be undone.
+    pub fn revert_revision(&self, clip_id: &str, revision_id: i64, now: i64) -> Result<Option<ClipItem>> {
+        let encrypted: Option<Vec<u8>> = self.conn.query_row(
+            "SELECT content FROM clip_revisions WHERE id = ?1 AND clip_id = ?2",
+            params![revision_id, clip_id],
+            |row| row.get(0),
+        ).optional()?;
+
+        match encrypted {
+            Some(encrypted) => {
+                let content = self.decrypt_content(encrypted)?;
+                self.update_clip_content(clip_id, &content, now)
             }
-        }).collect()
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_pinned(&self) -> Result<Vec<ClipItem>> {
+        self.query_clips(
+            &format!(
+                "SELECT {}
+                 FROM clips
+                 WHERE is_pinned = 1
+                 ORDER BY pin_order ASC",
+                CLIP_COLUMNS
+            ),
+            [],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_item(id: &str, text: &str, timestamp: i64) -> ClipItem {
+        ClipItem {
+            id: id.to_string(),
+            timestamp,
+            ..ClipItem::new(text.as_bytes().to_vec(), ContentType::Text)
+        }
+    }
+
+    fn content_match(start: usize, end: usize) -> FieldMatch {
+        FieldMatch { field: SearchField::Content, start, end }
+    }
+
+    fn pinned_ids(storage: &ClipStorage) -> Vec<String> {
+        storage.get_pinned().unwrap().into_iter().map(|item| item.id).collect()
+    }
+
+    #[test]
+    fn test_move_pin_renumbers() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        for (i, id) in ["a", "b", "c", "d"].iter().enumerate() {
+            storage.insert(&text_item(id, id, i as i64), 100).unwrap();
+            storage.update_pin(id, true).unwrap();
+        }
+
+        storage.move_pin("d", 0).unwrap();
+        assert_eq!(pinned_ids(&storage), vec!["d", "a", "b", "c"]);
+
+        storage.move_pin("a", 10).unwrap();
+        assert_eq!(pinned_ids(&storage), vec!["d", "b", "c", "a"]);
+
+        let orders: Vec<_> = storage.get_pinned().unwrap().iter().map(|i| i.pin_order).collect();
+        assert_eq!(orders, vec![Some(1), Some(2), Some(3), Some(4)]);
+
+        assert!(storage.move_pin("missing", 0).is_err());
+    }
+
+    #[test]
+    fn test_pin_groups() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        storage.insert(&text_item("a", "kubectl get pods", 1), 100).unwrap();
+
+        let group = storage.create_pin_group("Deploy").unwrap();
+        storage.assign_pin_group("a", Some(&group.id)).unwrap();
+
+        let item = storage.get_by_id("a").unwrap().unwrap();
+        assert!(item.is_pinned);
+        assert_eq!(item.pin_group_id.as_deref(), Some(group.id.as_str()));
+
+        storage.rename_pin_group(&group.id, "Ops").unwrap();
+        assert_eq!(storage.get_pin_groups().unwrap()[0].name, "Ops");
+        assert!(matches!(storage.rename_pin_group("missing", "Ops"), Err(rusqlite::Error::QueryReturnedNoRows)));
+
+        storage.delete_pin_group(&group.id).unwrap();
+        assert!(storage.get_pin_groups().unwrap().is_empty());
+        let item = storage.get_by_id("a").unwrap().unwrap();
+        assert!(item.is_pinned);
+        assert_eq!(item.pin_group_id, None);
+
+        assert!(storage.assign_pin_group("a", Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_title_and_note_are_searchable() {
+        let crypto = Arc::new(Crypto::new(&[7u8; 32]));
+        let storage = ClipStorage::new(":memory:", Some(crypto)).unwrap();
+        storage.insert(&text_item("a", "kubectl get pods -n production", 1), 100).unwrap();
+        storage.insert(&ClipItem {
+            id: "img".to_string(),
+            ..ClipItem::new(vec![1, 2, 3], ContentType::Image)
+        }, 100).unwrap();
+
+        let item = storage.update_clip_details("a", Some("List prod pods"), Some("  ")).unwrap().unwrap();
+        assert_eq!(item.title.as_deref(), Some("List prod pods"));
+        assert_eq!(item.note, None);
+        storage.update_clip_details("img", None, Some("Architecture diagram")).unwrap();
+
+        let ids = |query: &str| -> Vec<String> {
+            storage.search(&Query::parse(query).unwrap(), SortMode::Recent, 10).unwrap().into_iter().map(|r| r.item.id).collect()
+        };
+        assert_eq!(ids("prod pods"), vec!["a"]);
+        assert_eq!(ids("diagram"), vec!["img"]);
+    }
+
+    #[test]
+    fn test_tags_and_structured_search() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        storage.insert(&text_item("a", "kubectl rollout restart", 1), 100).unwrap();
+        storage.insert(&text_item("b", "SELECT * FROM users", 2), 100).unwrap();
+        storage.insert(&ClipItem {
+            id: "img".to_string(),
+            timestamp: 3,
+            ..ClipItem::new(vec![1, 2, 3], ContentType::Image)
+        }, 100).unwrap();
+        storage.update_pin("b", true).unwrap();
+
+        let item = storage.set_tags("a", &[" Deploy ".into(), "k8s".into(), "deploy".into()]).unwrap().unwrap();
+        assert_eq!(item.tags, vec!["deploy", "k8s"]);
+        storage.set_tags("img", &["deploy".into()]).unwrap();
+        assert_eq!(storage.get_all_tags().unwrap(), vec!["deploy", "k8s"]);
+        assert!(storage.set_tags("missing", &[]).unwrap().is_none());
+
+        let ids = |query: &str| -> Vec<String> {
+            storage.search(&Query::parse(query).unwrap(), SortMode::Recent, 10).unwrap().into_iter().map(|r| r.item.id).collect()
+        };
+        assert_eq!(ids("tag:deploy"), vec!["img", "a"]);
+        assert_eq!(ids("tag:deploy -type:image"), vec!["a"]);
+        assert_eq!(ids("pinned:yes OR rollout"), vec!["b", "a"]);
+        assert_eq!(ids("/^select/"), Vec::<String>::new());
+        assert_eq!(ids("/^SELECT/ before:today"), vec!["b"]);
+        assert_eq!(ids("type:image size:<1kb"), vec!["img"]);
+
+        // Tags are removed with the clip and reset by an empty list
+        storage.set_tags("img", &[]).unwrap();
+        storage.delete("a").unwrap();
+        assert!(storage.get_all_tags().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_relevance_ranking() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        let now = 100 * DAY_SECS;
+        storage.insert(&text_item("old-exact", "docker compose up", now - 60 * DAY_SECS), 100).unwrap();
+        storage.insert(&text_item("new-exact", "docker compose logs", now - 3 * DAY_SECS), 100).unwrap();
+        storage.insert(&text_item("abbrev", "docked server compose", now - 10 * DAY_SECS), 100).unwrap();
+        storage.insert(&text_item("typo", "dcoker compse down", now - 60), 100).unwrap();
+        storage.insert(&text_item("none", "kubectl get pods", now), 100).unwrap();
+
+        let ranked = |query: &str| -> Vec<String> {
+            storage.search(&Query::parse(query).unwrap(), SortMode::Relevance, now).unwrap()
+                .into_iter().map(|r| r.item.id).collect()
+        };
+
+        // Exact matches beat fuzzy ones even when older; recency breaks the tie
+        assert_eq!(ranked("docker compose"), vec!["new-exact", "old-exact", "abbrev", "typo"]);
+
+        // Pinning lifts an otherwise equal match
+        storage.update_pin("old-exact", true).unwrap();
+        assert_eq!(ranked("docker compose")[0], "old-exact");
+
+        // Other sort modes keep their order and still report highlights
+        let recent = storage.search(&Query::parse("compose").unwrap(), SortMode::Recent, now).unwrap();
+        assert_eq!(recent[0].item.id, "typo");
+        let new_exact = recent.iter().find(|r| r.item.id == "new-exact").unwrap();
+        assert_eq!(new_exact.matches, vec![content_match(7, 14)]);
+    }
+
+    #[test]
+    fn test_cjk_search() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        storage.insert(&text_item("server", "连接服务器 nginx", 1), 100).unwrap();
+        storage.insert(&text_item("db", "数据库 backup 完成", 2), 100).unwrap();
+        storage.insert(&text_item("wide", "ＡＰＩ　Ｋｅｙ：ａｂｃ１２３", 3), 100).unwrap();
+        storage.insert(&text_item("english", "restart the web server", 4), 100).unwrap();
+
+        let search = |query: &str| storage.search(&Query::parse(query).unwrap(), SortMode::Relevance, 10).unwrap();
+        let ids = |query: &str| -> Vec<String> { search(query).into_iter().map(|r| r.item.id).collect() };
+
+        // Pinyin initials and full pinyin, highlighted on the Chinese characters
+        let results = search("fwq");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item.id, "server");
+        assert_eq!(results[0].matches, vec![content_match(2, 5)]);
+        assert_eq!(ids("shujuku backup"), vec!["db"]);
+        assert_eq!(ids("sjk 完成"), vec!["db"]);
+        assert_eq!(ids("服务器"), vec!["server"]);
+
+        // Mixed scripts in one query
+        assert_eq!(ids("nginx lj"), vec!["server"]);
+
+        // Full-width text matches half-width queries and the other way round
+        let results = search("api key");
+        assert_eq!(results[0].item.id, "wide");
+        assert_eq!(results[0].matches, vec![content_match(0, 3), content_match(4, 7)]);
+        assert_eq!(ids("\"key:abc123\""), vec!["wide"]);
+        assert_eq!(ids("ＲＥＳＴＡＲＴ"), vec!["english"]);
+    }
+
+    #[test]
+    fn test_search_all_content_types() {
+        let crypto = Arc::new(Crypto::new(&[7u8; 32]));
+        let storage = ClipStorage::new(":memory:", Some(crypto)).unwrap();
+        let item = |id: &str, content: &[u8], content_type: ContentType| ClipItem {
+            id: id.to_string(),
+            ..ClipItem::new(content.to_vec(), content_type)
+        };
+        storage.insert(&item("html", b"<h1>Deploy checklist</h1>", ContentType::Html), 100).unwrap();
+        storage.insert(&item("rtf", br"{\rtf1 Deploy {\i notes}}", ContentType::Rtf), 100).unwrap();
+        storage.insert(&item("file", b"/srv/deploy/run.sh", ContentType::File), 100).unwrap();
+        storage.insert(&item("img", &[1, 2, 3], ContentType::Image), 100).unwrap();
+        storage.update_clip_details("img", Some("Deploy diagram"), None).unwrap();
+
+        let results = storage.search(&Query::parse("deploy").unwrap(), SortMode::Recent, 10).unwrap();
+        let mut found: Vec<(String, Vec<SearchField>)> = results.into_iter().map(|r| (r.item.id, r.matched_fields)).collect();
+        found.sort();
+        assert_eq!(found, vec![
+            ("file".to_string(), vec![SearchField::Path]),
+            ("html".to_string(), vec![SearchField::Content]),
+            ("img".to_string(), vec![SearchField::Title]),
+            ("rtf".to_string(), vec![SearchField::Content]),
+        ]);
+    }
+
+    #[test]
+    fn test_html_plain_text_is_stored() {
+        let crypto = Arc::new(Crypto::new(&[7u8; 32]));
+        let storage = ClipStorage::new(":memory:", Some(crypto)).unwrap();
+        storage.insert(&ClipItem {
+            id: "html".to_string(),
+            plain_text: Some("  Budget plan\n".to_string()),
+            ..ClipItem::new(b"<p>Budget <b>plan</b></p>".to_vec(), ContentType::Html)
+        }, 100).unwrap();
+
+        let item = storage.get_by_id("html").unwrap().unwrap();
+        assert_eq!(item.plain_text.as_deref(), Some("  Budget plan\n"));
+
+        // Editing the HTML drops the alternative captured with the old markup
+        let item = storage.update_clip_content("html", b"<p>Budget v2</p>", 5).unwrap().unwrap();
+        assert_eq!(item.plain_text, None);
+    }
+
+    #[test]
+    fn test_sensitive_flag() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        storage.insert(&text_item("a", "4111 1111 1111 1111", 1), 100).unwrap();
+        assert!(!storage.get_by_id("a").unwrap().unwrap().sensitive);
+
+        // Copying the same secret again flags the existing clip
+        let again = ClipItem { sensitive: true, ..text_item("b", "4111 1111 1111 1111", 2) };
+        assert_eq!(storage.insert_with_formats(&again, &[], 100).unwrap().as_deref(), Some("a"));
+        assert!(storage.get_by_id("a").unwrap().unwrap().sensitive);
+
+        assert!(!storage.set_sensitive("a", false).unwrap().unwrap().sensitive);
+        assert!(storage.set_sensitive("missing", true).unwrap().is_none());
+        assert_eq!(storage.set_clear_after("a", Some(30)).unwrap().unwrap().clear_after_secs, Some(30));
+    }
+
+    #[test]
+    fn test_text_kind() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        let url = ClipItem { text_kind: Some(TextKind::Url), ..text_item("a", "https://example.com", 1) };
+        storage.insert(&url, 100).unwrap();
+        storage.insert(&text_item("b", "ops@example.com", 2), 100).unwrap();
+        assert_eq!(storage.get_by_id("a").unwrap().unwrap().text_kind, Some(TextKind::Url));
+
+        // Clips stored without a label get one
+        assert_eq!(storage.classify_unlabelled().unwrap(), 1);
+        assert_eq!(storage.get_by_id("b").unwrap().unwrap().text_kind, Some(TextKind::Email));
+        assert_eq!(storage.classify_unlabelled().unwrap(), 0);
+
+        // Clips without a label aren't of any kind, so negating a kind keeps them
+        storage.insert(&ClipItem { id: "img".to_string(), timestamp: 3, ..ClipItem::new(vec![1, 2, 3], ContentType::Image) }, 100).unwrap();
+        let results = storage.search(&Query::parse("-kind:url").unwrap(), SortMode::Recent, 10).unwrap();
+        let mut found: Vec<String> = results.into_iter().map(|result| result.item.id).collect();
+        found.sort();
+        assert_eq!(found, vec!["b", "img"]);
+        let results = storage.search(&Query::parse("kind:url").unwrap(), SortMode::Recent, 10).unwrap();
+        assert_eq!(results.into_iter().map(|result| result.item.id).collect::<Vec<_>>(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_selection() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        let selected = ClipItem { selection: Selection::Primary, ..text_item("a", "selected", 1) };
+        storage.insert(&selected, 100).unwrap();
+        assert_eq!(storage.get_by_id("a").unwrap().unwrap().selection, Selection::Primary);
+
+        // Selecting it again keeps it a selection; copying it makes it a clipboard clip
+        storage.insert(&ClipItem { timestamp: 2, ..selected }, 100).unwrap();
+        assert_eq!(storage.get_by_id("a").unwrap().unwrap().selection, Selection::Primary);
+        storage.insert(&text_item("b", "selected", 3), 100).unwrap();
+        assert_eq!(storage.get_by_id("a").unwrap().unwrap().selection, Selection::Clipboard);
+    }
+
+    #[test]
+    fn test_clip_formats() {
+        let crypto = Arc::new(Crypto::new(&[7u8; 32]));
+        let storage = ClipStorage::new(":memory:", Some(crypto)).unwrap();
+        let formats = vec![
+            ClipFormat { content_type: ContentType::Rtf, data: br"{\rtf1 Budget}".to_vec() },
+            ClipFormat { content_type: ContentType::Image, data: vec![0x89, b'P', b'N', b'G'] },
+        ];
+        let item = ClipItem {
+            id: "doc".to_string(),
+            plain_text: Some("Budget".to_string()),
+            ..ClipItem::new(b"<b>Budget</b>".to_vec(), ContentType::Html)
+        };
+        assert_eq!(storage.insert_with_formats(&item, &formats, 100).unwrap(), None);
+        assert_eq!(storage.get_formats("doc").unwrap(), formats);
+        assert!(storage.get_formats("missing").unwrap().is_empty());
+
+        // A duplicate capture replaces the formats with the ones offered this time
+        let again = ClipItem { id: "again".to_string(), ..item.clone() };
+        assert_eq!(storage.insert_with_formats(&again, &formats[..1], 100).unwrap().as_deref(), Some("doc"));
+        assert_eq!(storage.get_formats("doc").unwrap(), formats[..1]);
+
+        // Edits drop them, deletes cascade
+        storage.update_clip_content("doc", b"<b>Budget v2</b>", 5).unwrap();
+        assert!(storage.get_formats("doc").unwrap().is_empty());
+        storage.insert_with_formats(&ClipItem { id: "img".to_string(), ..ClipItem::new(vec![1], ContentType::Image) }, &formats, 100).unwrap();
+        storage.delete("img").unwrap();
+        let orphans: i64 = storage.conn.query_row("SELECT COUNT(*) FROM clip_formats", [], |row| row.get(0)).unwrap();
+        assert_eq!(orphans, 0);
+    }
+
+    #[test]
+    fn test_saved_searches() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        storage.insert(&text_item("sql", "SELECT * FROM orders", 1), 100).unwrap();
+        storage.insert(&text_item("url", "https://example.com/docs", 2), 100).unwrap();
+        storage.set_tags("sql", &["sql".into()]).unwrap();
+
+        let mut search = SavedSearch {
+            id: "s1".to_string(),
+            name: "SQL snippets".to_string(),
+            query: "type:text tag:sql".to_string(),
+            sort: SortMode::MostUsed,
+            show_in_tray: false,
+            created_at: 5,
+        };
+        storage.save_search(&search).unwrap();
+        storage.save_search(&SavedSearch {
+            id: "s2".to_string(),
+            name: "Links".to_string(),
+            query: "/^https?:/".to_string(),
+            sort: SortMode::Recent,
+            show_in_tray: true,
+            created_at: 6,
+        }).unwrap();
+
+        // Saving again updates in place and keeps the creation time
+        search.name = "SQL".to_string();
+        search.show_in_tray = true;
+        search.created_at = 99;
+        storage.save_search(&search).unwrap();
+        let saved = storage.get_saved_search("s1").unwrap().unwrap();
+        assert_eq!(saved.name, "SQL");
+        assert!(saved.show_in_tray);
+        assert_eq!(saved.sort, SortMode::MostUsed);
+        assert_eq!(saved.created_at, 5);
+
+        let names: Vec<_> = storage.get_saved_searches().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["SQL", "Links"]);
+
+        assert_eq!(storage.saved_searches_matching("sql").unwrap(), vec!["s1"]);
+        assert_eq!(storage.saved_searches_matching("url").unwrap(), vec!["s2"]);
+        assert!(storage.saved_searches_matching("missing").unwrap().is_empty());
+
+        storage.delete_saved_search("s2").unwrap();
+        assert!(storage.get_saved_search("s2").unwrap().is_none());
+        assert!(storage.saved_searches_matching("url").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_frecency_score() {
+        let now = 1_000 * DAY_SECS;
+        // Recently used beats used more often but long ago
+        assert!(frecency_score(3, Some(now - DAY_SECS), 0, now) > frecency_score(10, Some(now - 200 * DAY_SECS), 0, now));
+        // Never used falls back to the capture time
+        assert_eq!(frecency_score(0, None, now, now), 100);
+        assert_eq!(frecency_score(0, None, now - 100 * DAY_SECS, now), 10);
+    }
+
+    #[test]
+    fn test_sort_modes() {
+        let now = chrono::Utc::now().timestamp();
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        storage.insert(&text_item("daily", "kubectl get pods", now - 20 * DAY_SECS), 100).unwrap();
+        storage.insert(&text_item("big", &"x".repeat(500), now - 2 * DAY_SECS), 100).unwrap();
+        storage.insert(&text_item("new", "just copied", now), 100).unwrap();
+        for _ in 0..10 {
+            storage.record_use("daily", now - 60).unwrap();
+        }
+
+        let ids = |limit: usize, sort: SortMode| -> Vec<String> {
+            storage.get_recent(limit, sort).unwrap().into_iter().map(|i| i.id).collect()
+        };
+        assert_eq!(ids(10, SortMode::Recent), vec!["new", "big", "daily"]);
+        assert_eq!(ids(10, SortMode::MostUsed)[0], "daily");
+        assert_eq!(ids(10, SortMode::Size)[0], "big");
+        // Frequently pasted snippets surface even when older than the limit window
+        assert_eq!(ids(1, SortMode::Frecency), vec!["daily"]);
+
+        let item = storage.get_by_id("daily").unwrap().unwrap();
+        assert_eq!(item.copy_count, 10);
+        assert_eq!(item.last_used_at, Some(now - 60));
+    }
+
+    #[test]
+    fn test_expiry_and_burn_after_paste() {
+        let storage = ClipStorage::new(":memory:", None).unwrap();
+        storage.insert(&ClipItem { expires_at: Some(100), ..text_item("otp", "123456", 1) }, 100).unwrap();
+        storage.insert(&text_item("token", "secret-token", 2), 100).unwrap();
+        storage.insert(&text_item("keep", "keep me", 3), 100).unwrap();
+
+        storage.set_paste_limit("token", Some(2)).unwrap();
+        assert!(!storage.record_paste("token").unwrap());
+        assert_eq!(storage.get_by_id("token").unwrap().unwrap().pastes_remaining, Some(1));
+        assert!(storage.record_paste("token").unwrap());
+        assert!(storage.get_by_id("token").unwrap().is_none());
+
+        // Clips without a paste limit are unaffected
+        assert!(!storage.record_paste("keep").unwrap());
+
+        assert!(storage.delete_expired(99).unwrap().is_empty());
+        assert_eq!(storage.delete_expired(100).unwrap(), vec!["otp"]);
+        assert!(storage.get_by_id("otp").unwrap().is_none());
+        assert!(storage.get_by_id("keep").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_update_content_keeps_revisions() {
+        let crypto = Arc::new(Crypto::new(&[7u8; 32]));
+        let storage = ClipStorage::new(":memory:", Some(crypto)).unwrap();
+        storage.insert(&text_item("a", "helo world", 1), 100).unwrap();
+
+        let updated = storage.update_clip_content("a", b"hello world", 2).unwrap().unwrap();
+        assert_eq!(updated.content, b"hello world");
+
+        let revisions = storage.list_revisions("a").unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].content, "helo world");
+
+        // Dedup now matches the edited content, not the original
+        let duplicate = storage.insert(&text_item("b", "hello world", 3), 100).unwrap();
+        assert_eq!(duplicate.as_deref(), Some("a"));
+        let original = storage.insert(&text_item("c", "helo world", 4), 100).unwrap();
+        assert_eq!(original, None);
+
+        let reverted = storage.revert_revision("a", revisions[0].id, 5).unwrap().unwrap();
+        assert_eq!(reverted.content, b"helo world");
+        assert_eq!(storage.list_revisions("a").unwrap().len(), 2);
+
+        storage.delete("a").unwrap();
+        assert!(storage.list_revisions("a").unwrap().is_empty());
+        assert!(storage.update_clip_content("a", b"x", 6).unwrap().is_none());
     }
 }
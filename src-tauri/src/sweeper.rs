@@ -0,0 +1,45 @@
+// Background removal of expired clips
+use tauri::{AppHandle, Emitter, Manager};
+use chrono::Utc;
+use std::time::Duration;
+
+use crate::tray::update_tray_menu;
+use crate::{AppState, safe_lock};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Notify the tray and window that clips were removed without user action
+/// (expired or burned after their last paste)
+pub fn notify_clips_expired(app: &AppHandle, ids: &[String]) {
+    if ids.is_empty() {
+        return;
+    }
+
+    update_tray_menu(app);
+
+    if let Err(e) = app.emit("clips-expired", ids) {
+        log::error!("Failed to emit clips-expired event: {}", e);
+    }
+}
+
+/// Start a thread that periodically deletes clips past their `expires_at`
+pub fn start_expiry_sweeper(app: AppHandle) {
+    std::thread::spawn(move || {
+        log::info!("Expiry sweeper started (every {}s)", SWEEP_INTERVAL.as_secs());
+
+        loop {
+            std::thread::sleep(SWEEP_INTERVAL);
+
+            let state = app.state::<AppState>();
+            let result = {
+                let storage = safe_lock(&state.storage);
+                storage.delete_expired(Utc::now().timestamp())
+            };
+
+            match result {
+                Ok(ids) => notify_clips_expired(&app, &ids),
+                Err(e) => log::error!("Failed to delete expired clips: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,203 @@
+// Transform rules: user-configurable cleanups applied to captured text before it is stored
+//
+// Rules run in order and each one sees the output of the previous. Built-in kinds cover
+// common cleanups (whitespace, line endings, URL tracking parameters, terminal prompts);
+// `replace` rules apply a user regex.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Query parameters that only track where a link was shared
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "dclid", "msclkid", "mc_eid", "igshid", "yclid"];
+
+/// What a transform rule does to the text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TransformKind {
+    /// Remove leading and trailing whitespace
+    TrimWhitespace,
+    /// Convert `\r\n` and `\r` line endings to `\n`
+    NormalizeLineEndings,
+    /// Remove `utm_*`, `fbclid` and similar parameters from URLs
+    StripTrackingParams,
+    /// Remove a shell prompt left at the end of a terminal copy, and `$ ` markers in
+    /// front of every command
+    StripShellPrompt,
+    /// Replace every match of a regular expression (`$1` refers to groups)
+    #[serde(rename_all = "camelCase")]
+    Replace { pattern: String, replacement: String },
+}
+
+/// A toggleable step of the transform pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformRule {
+    pub name: String,
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub kind: TransformKind,
+}
+
+/// Text after the transform rules, with the names of the rules that changed it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformPreview {
+    pub text: String,
+    pub applied: Vec<String>,
+}
+
+/// Built-in rules, disabled until the user turns them on
+pub fn default_transform_rules() -> Vec<TransformRule> {
+    let rule = |name: &str, kind| TransformRule { name: name.to_string(), enabled: false, kind };
+    vec![
+        rule("Normalize line endings", TransformKind::NormalizeLineEndings),
+        rule("Strip shell prompts", TransformKind::StripShellPrompt),
+        rule("Strip URL tracking parameters", TransformKind::StripTrackingParams),
+        rule("Trim whitespace", TransformKind::TrimWhitespace),
+    ]
+}
+
+/// Run the enabled rules over `text` in order
+pub fn apply_transforms(text: &str, rules: &[TransformRule]) -> TransformPreview {
+    let mut text = text.to_string();
+    let mut applied = Vec::new();
+
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let transformed = match &rule.kind {
+            TransformKind::TrimWhitespace => text.trim().to_string(),
+            TransformKind::NormalizeLineEndings => text.replace("\r\n", "\n").replace('\r', "\n"),
+            TransformKind::StripTrackingParams => strip_tracking_params(&text),
+            TransformKind::StripShellPrompt => strip_shell_prompt(&text),
+            TransformKind::Replace { pattern, replacement } => match Regex::new(pattern) {
+                Ok(regex) => regex.replace_all(&text, replacement.as_str()).into_owned(),
+                Err(e) => {
+                    log::warn!("⚠️ Invalid pattern in transform rule '{}': {}", rule.name, e);
+                    continue;
+                }
+            },
+        };
+
+        if transformed != text {
+            applied.push(rule.name.clone());
+            text = transformed;
+        }
+    }
+
+    TransformPreview { text, applied }
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+/// `url` without tracking parameters. The `?` goes too if nothing else is left.
+fn clean_url(url: &str) -> String {
+    let (rest, fragment) = url.split_at(url.find('#').unwrap_or(url.len()));
+    let Some((base, query)) = rest.split_once('?') else {
+        return url.to_string();
+    };
+
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| !param.is_empty() && !is_tracking_param(param.split('=').next().unwrap_or_default()))
+        .collect();
+    if kept.is_empty() {
+        format!("{}{}", base, fragment)
+    } else {
+        format!("{}?{}{}", base, kept.join("&"), fragment)
+    }
+}
+
+fn strip_tracking_params(text: &str) -> String {
+    let urls = Regex::new(r#"https?://[^\s<>"']+"#).expect("valid URL pattern");
+    urls.replace_all(text, |caps: &regex::Captures| clean_url(&caps[0])).into_owned()
+}
+
+/// Whether a line is only a shell prompt (`$`, `user@host:~/src$`, `❯`, `PS C:\src>`)
+fn is_prompt_line(line: &str) -> bool {
+    let line = line.trim();
+    let line = line.strip_prefix("PS ").unwrap_or(line);
+    line.ends_with(['$', '#', '%', '>', '❯']) && !line.contains(char::is_whitespace)
+}
+
+fn strip_shell_prompt(text: &str) -> String {
+    let mut lines: Vec<&str> = text.lines().collect();
+    while lines.last().is_some_and(|line| is_prompt_line(line)) {
+        lines.pop();
+    }
+
+    // `$ ` in front of every command, as in docs and shell transcripts
+    let commands: Vec<&str> = lines.iter().copied().filter(|line| !line.trim().is_empty()).collect();
+    if !commands.is_empty() && commands.iter().all(|line| line.starts_with("$ ")) {
+        for line in &mut lines {
+            *line = line.strip_prefix("$ ").unwrap_or(line);
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(kind: TransformKind) -> TransformRule {
+        TransformRule { name: format!("{:?}", kind), enabled: true, kind }
+    }
+
+    #[test]
+    fn test_builtin_transforms() {
+        let apply = |kind, text: &str| apply_transforms(text, &[enabled(kind)]).text;
+
+        assert_eq!(apply(TransformKind::TrimWhitespace, "  token \n"), "token");
+        assert_eq!(apply(TransformKind::NormalizeLineEndings, "a\r\nb\rc\n"), "a\nb\nc\n");
+        assert_eq!(
+            apply(
+                TransformKind::StripTrackingParams,
+                "see https://example.com/post?id=7&utm_source=x&fbclid=abc#top and https://a.io/?utm_medium=mail"
+            ),
+            "see https://example.com/post?id=7#top and https://a.io/"
+        );
+        assert_eq!(apply(TransformKind::StripTrackingParams, "https://example.com/?q=utm"), "https://example.com/?q=utm");
+        assert_eq!(
+            apply(TransformKind::StripShellPrompt, "total 8\ndrwxr-xr-x 2 me me 4096 .\nme@box:~/src$ "),
+            "total 8\ndrwxr-xr-x 2 me me 4096 ."
+        );
+        assert_eq!(apply(TransformKind::StripShellPrompt, "$ npm install\n$ npm test\n❯"), "npm install\nnpm test");
+        // A `$` inside the text is not a prompt
+        assert_eq!(apply(TransformKind::StripShellPrompt, "echo $HOME\ncosts 5 $"), "echo $HOME\ncosts 5 $");
+    }
+
+    #[test]
+    fn test_rules_run_in_order_and_can_be_disabled() {
+        let replace = |pattern: &str, replacement: &str| {
+            enabled(TransformKind::Replace { pattern: pattern.to_string(), replacement: replacement.to_string() })
+        };
+        let mut rules = vec![replace(r"(\d+) USD", "$$$1"), enabled(TransformKind::TrimWhitespace)];
+
+        let preview = apply_transforms(" 40 USD ", &rules);
+        assert_eq!(preview.text, "$40");
+        assert_eq!(preview.applied.len(), 2);
+
+        // Rules that change nothing aren't reported; disabled and invalid rules are skipped
+        rules[0].enabled = false;
+        rules.push(replace("(", "x"));
+        let preview = apply_transforms("40 USD", &rules);
+        assert_eq!(preview, TransformPreview { text: "40 USD".to_string(), applied: Vec::new() });
+        assert!(default_transform_rules().iter().all(|rule| !rule.enabled));
+    }
+
+    #[test]
+    fn test_rule_serialization() {
+        let rule = TransformRule {
+            name: "Redact".to_string(),
+            enabled: true,
+            kind: TransformKind::Replace { pattern: "a".to_string(), replacement: "b".to_string() },
+        };
+        let json = serde_json::to_value(&rule).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "name": "Redact", "enabled": true, "kind": "replace", "pattern": "a", "replacement": "b"
+        }));
+        assert_eq!(serde_json::from_value::<TransformRule>(json).unwrap(), rule);
+        assert_eq!(serde_json::to_value(enabled(TransformKind::TrimWhitespace)).unwrap()["kind"], "trimWhitespace");
+    }
+}
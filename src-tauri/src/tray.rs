@@ -1,11 +1,16 @@
 // Tray menu management module
 use tauri::{AppHandle, Manager};
-use tauri::menu::{MenuBuilder, MenuItemBuilder, IconMenuItemBuilder};
+use tauri::menu::{MenuBuilder, MenuItemBuilder, IconMenuItemBuilder, SubmenuBuilder};
 use lru::LruCache;
 use std::sync::Mutex;
 use std::num::NonZeroUsize;
 
-use crate::storage::{ClipItem, ContentType};
+use crate::query::Query;
+use crate::capture::plain_text;
+use crate::classify::{parse_color, TextKind};
+use crate::richtext::{html_to_text, rtf_to_text};
+use crate::sensitive::mask_sensitive;
+use crate::storage::{ClipItem, ContentType, SavedSearch};
 use crate::AppState;
 
 // Tray configuration constants
@@ -16,9 +21,12 @@ const ICON_CACHE_SIZE: usize = 50;
 pub struct TrayI18n {
     pub pinned_header: &'static str,
     pub recent_header: &'static str,
+    pub saved_searches_header: &'static str,
     pub image: &'static str,
     pub file: &'static str,
     pub file_prefix: &'static str,
+    /// Follows the count in "3 files: a.txt, …"
+    pub files_suffix: &'static str,
     pub clear: &'static str,
     pub settings: &'static str,
     pub quit: &'static str,
@@ -30,9 +38,11 @@ impl TrayI18n {
             Self {
                 pinned_header: "置顶项",
                 recent_header: "最近复制",
+                saved_searches_header: "已保存的搜索",
                 image: "图片",
                 file: "文件",
                 file_prefix: "文件: ",
+                files_suffix: " 个文件: ",
                 clear: "清除",
                 settings: "设置",
                 quit: "退出",
@@ -41,9 +51,11 @@ impl TrayI18n {
             Self {
                 pinned_header: "Pinned",
                 recent_header: "Recent",
+                saved_searches_header: "Saved Searches",
                 image: "Image",
                 file: "File",
                 file_prefix: "File: ",
+                files_suffix: " files: ",
                 clear: "Clear",
                 settings: "Settings",
                 quit: "Quit",
@@ -126,6 +138,12 @@ pub fn truncate_content(content: &[u8], content_type: &ContentType, max_len: usi
     match content_type {
         ContentType::Text | ContentType::Html | ContentType::Rtf => {
             let text = String::from_utf8_lossy(content);
+            // Render markup so the menu shows readable text
+            let text = match content_type {
+                ContentType::Html => html_to_text(&text),
+                ContentType::Rtf => rtf_to_text(&text),
+                _ => text.into_owned(),
+            };
             // Replace newlines and carriage returns, then collapse whitespace
             let text: String = text.chars()
                 .map(|c| if c == '\n' || c == '\r' { ' ' } else { c })
@@ -149,34 +167,89 @@ pub fn truncate_content(content: &[u8], content_type: &ContentType, max_len: usi
         }
         ContentType::Image => i18n.image.to_string(),
         ContentType::File => {
-            match std::str::from_utf8(content) {
-                Ok(path_str) => {
-                    let path = std::path::Path::new(path_str);
-                    if let Some(file_name) = path.file_name() {
-                        format!("{}{}", i18n.file_prefix, file_name.to_string_lossy())
-                    } else {
-                        i18n.file.to_string()
+            let names = crate::capture::file_names(&crate::capture::parse_file_list(content));
+            match names.as_slice() {
+                [] => i18n.file.to_string(),
+                [name] => format!("{}{}", i18n.file_prefix, name),
+                _ => {
+                    // List as many names as fit, the first one always
+                    let mut preview = format!("{}{}{}", names.len(), i18n.files_suffix, names[0]);
+                    for name in &names[1..] {
+                        if preview.chars().count() + 2 + name.chars().count() > max_len {
+                            preview.push_str(", …");
+                            break;
+                        }
+                        preview.push_str(", ");
+                        preview.push_str(name);
                     }
-                }
-                Err(e) => {
-                    log::warn!("Invalid UTF-8 in file path content: {}", e);
-                    i18n.file.to_string()
+                    preview
                 }
             }
         },
     }
 }
 
+/// Menu label of a clip. Secrets in sensitive clips are masked unless `mask` is off.
+pub fn clip_preview(item: &ClipItem, max_len: usize, mask: bool, i18n: &TrayI18n) -> String {
+    // A user-given title describes the clip better than its (truncated) content
+    match (&item.title, &item.plain_text) {
+        (Some(title), _) => truncate_content(title.as_bytes(), &ContentType::Text, max_len, i18n),
+        (None, _) if mask && item.sensitive => {
+            truncate_content(mask_sensitive(&plain_text(item)).as_bytes(), &ContentType::Text, max_len, i18n)
+        }
+        // Rich text is previewed as the plain text captured with it
+        (None, Some(text)) => truncate_content(text.as_bytes(), &ContentType::Text, max_len, i18n),
+        (None, None) => truncate_content(&item.content, &item.content_type, max_len, i18n),
+    }
+}
+
+/// Symbol shown before the label of text clips, by what the text holds
+fn kind_symbol(kind: TextKind) -> Option<&'static str> {
+    match kind {
+        TextKind::Url => Some("🔗"),
+        TextKind::Email => Some("✉️"),
+        TextKind::Phone => Some("📞"),
+        TextKind::Color => Some("🎨"),
+        TextKind::Json => Some("🧾"),
+        TextKind::Code => Some("💻"),
+        TextKind::Path => Some("📁"),
+        TextKind::Number => Some("🔢"),
+        TextKind::Prose => None,
+    }
+}
+
+/// A square of the clip's colour, for colour clips
+fn color_swatch(item: &ClipItem) -> Option<tauri::image::Image<'static>> {
+    if item.text_kind != Some(TextKind::Color) {
+        return None;
+    }
+    let rgba = parse_color(&plain_text(item))?;
+    let pixels = rgba.repeat((TRAY_ICON_SIZE * TRAY_ICON_SIZE) as usize);
+    Some(tauri::image::Image::new_owned(pixels, TRAY_ICON_SIZE, TRAY_ICON_SIZE))
+}
+
 /// Helper function to add a clip menu item
 fn add_clip_menu_item(
     app: &AppHandle,
     item: &ClipItem,
     icon_cache: &TrayIconCache,
     max_len: usize,
+    mask_sensitive: bool,
     i18n: &TrayI18n,
 ) -> Result<Box<dyn tauri::menu::IsMenuItem<tauri::Wry>>, tauri::Error> {
-    let preview = truncate_content(&item.content, &item.content_type, max_len, i18n);
+    let preview = clip_preview(item, max_len, mask_sensitive, i18n);
     
+    if let Some(swatch) = color_swatch(item) {
+        let menu_item = IconMenuItemBuilder::with_id(format!("clip:{}", item.id), preview)
+            .icon(swatch)
+            .build(app)?;
+        return Ok(Box::new(menu_item));
+    }
+    let preview = match item.text_kind.and_then(kind_symbol) {
+        Some(symbol) => format!("{} {}", symbol, preview),
+        None => preview,
+    };
+
     if matches!(item.content_type, ContentType::Image) {
         if let Some(icon) = icon_cache.get_or_create(&item.id, &item.content) {
             let menu_item = IconMenuItemBuilder::with_id(
@@ -211,30 +284,81 @@ pub fn build_tray_menu(app: &AppHandle) -> Result<tauri::menu::Menu<tauri::Wry>,
     let max_pinned_in_tray = settings.max_pinned_in_tray;
     let max_recent_in_tray = settings.max_recent_in_tray;
     let max_len = settings.tray_text_length;
+    let mask_sensitive = settings.mask_sensitive;
     let i18n = TrayI18n::new(&settings.locale);
     
     // Calculate query limit
     let query_limit = (max_recent_in_tray + max_pinned_in_tray).max(30);
     
     // Quick lock acquisition - get data and release immediately
-    let (pinned_items, recent_items) = {
+    let (pinned_items, pin_groups, recent_items, saved_searches) = {
         let storage = crate::safe_lock(&state.storage);
+        let now = chrono::Utc::now().timestamp();
+
+        // Saved searches shown in the tray, with their top results
+        let saved_searches: Vec<(SavedSearch, Vec<ClipItem>)> = storage.get_saved_searches()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|search| search.show_in_tray)
+            .map(|search| {
+                let items = Query::parse(&search.query)
+                    .ok()
+                    .and_then(|query| storage.search(&query, search.sort, now).ok())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .take(max_recent_in_tray)
+                    .map(|result| result.item)
+                    .collect();
+                (search, items)
+            })
+            .collect();
+
         (
             storage.get_pinned().unwrap_or_default(),
-            storage.get_recent(query_limit).unwrap_or_default(),
+            storage.get_pin_groups().unwrap_or_default(),
+            storage.get_recent(query_limit, settings.tray_sort_mode).unwrap_or_default(),
+            saved_searches,
         )
     };
     
     let mut menu_builder = MenuBuilder::new(app);
 
+    // Grouped pins go into one submenu per group; the rest form the capped pinned list.
+    // Items pointing at a group that no longer exists are treated as ungrouped.
+    let is_grouped = |item: &ClipItem| {
+        item.pin_group_id.as_ref()
+            .is_some_and(|group_id| pin_groups.iter().any(|group| &group.id == group_id))
+    };
+    let ungrouped_pins: Vec<_> = pinned_items.iter()
+        .filter(|item| !is_grouped(item))
+        .take(max_pinned_in_tray)
+        .collect();
+    let has_grouped_pins = pinned_items.iter().any(|item| is_grouped(item));
+
     // Add pinned items
-    let pinned_count = pinned_items.len().min(max_pinned_in_tray);
-    if pinned_count > 0 {
+    if !ungrouped_pins.is_empty() || has_grouped_pins {
         let pinned_header = MenuItemBuilder::with_id("pinned_header", i18n.pinned_header).enabled(false).build(app)?;
         menu_builder = menu_builder.item(&pinned_header);
 
-        for item in pinned_items.iter().take(max_pinned_in_tray) {
-            let menu_item = add_clip_menu_item(app, item, &state.icon_cache, max_len, &i18n)?;
+        for group in &pin_groups {
+            let group_items: Vec<_> = pinned_items.iter()
+                .filter(|item| item.pin_group_id.as_deref() == Some(group.id.as_str()))
+                .collect();
+
+            if group_items.is_empty() {
+                continue;
+            }
+
+            let mut submenu_builder = SubmenuBuilder::with_id(app, format!("pin_group:{}", group.id), &group.name);
+            for item in group_items {
+                let menu_item = add_clip_menu_item(app, item, &state.icon_cache, max_len, mask_sensitive, &i18n)?;
+                submenu_builder = submenu_builder.item(&*menu_item);
+            }
+            menu_builder = menu_builder.item(&submenu_builder.build()?);
+        }
+
+        for item in ungrouped_pins {
+            let menu_item = add_clip_menu_item(app, item, &state.icon_cache, max_len, mask_sensitive, &i18n)?;
             menu_builder = menu_builder.item(&*menu_item);
         }
 
@@ -252,11 +376,27 @@ pub fn build_tray_menu(app: &AppHandle) -> Result<tauri::menu::Menu<tauri::Wry>,
         menu_builder = menu_builder.item(&recent_header);
 
         for item in recent_unpinned {
-            let menu_item = add_clip_menu_item(app, item, &state.icon_cache, max_len, &i18n)?;
+            let menu_item = add_clip_menu_item(app, item, &state.icon_cache, max_len, mask_sensitive, &i18n)?;
             menu_builder = menu_builder.item(&*menu_item);
         }
     }
 
+    // Saved searches, one submenu each (disabled while nothing matches)
+    if !saved_searches.is_empty() {
+        let saved_header = MenuItemBuilder::with_id("saved_searches_header", i18n.saved_searches_header).enabled(false).build(app)?;
+        menu_builder = menu_builder.separator().item(&saved_header);
+
+        for (search, items) in &saved_searches {
+            let mut submenu_builder = SubmenuBuilder::with_id(app, format!("saved_search:{}", search.id), &search.name)
+                .enabled(!items.is_empty());
+            for item in items {
+                let menu_item = add_clip_menu_item(app, item, &state.icon_cache, max_len, mask_sensitive, &i18n)?;
+                submenu_builder = submenu_builder.item(&*menu_item);
+            }
+            menu_builder = menu_builder.item(&submenu_builder.build()?);
+        }
+    }
+
     // Bottom actions
     menu_builder = menu_builder
         .separator()
@@ -338,4 +478,35 @@ mod tests {
         assert!(!result.contains('\n'));
         assert!(!result.contains('\r'));
     }
+
+    #[test]
+    fn test_truncate_content_rich_text() {
+        let i18n = TrayI18n::new("en");
+        let rtf = br"{\rtf1\ansi{\fonttbl{\f0 Arial;}}\f0 Quarterly {\b report}\par Q3}";
+        assert_eq!(truncate_content(rtf, &ContentType::Rtf, 50, &i18n), "Quarterly report Q3");
+        assert_eq!(truncate_content(b"<p>A &amp; <b>B</b></p>", &ContentType::Html, 50, &i18n), "A & B");
+    }
+
+    #[test]
+    fn test_truncate_content_files() {
+        let i18n = TrayI18n::new("en");
+        let files = b"/home/me/a.txt\n/home/me/b.txt\n/home/me/c.txt";
+        assert_eq!(truncate_content(files, &ContentType::File, 16, &i18n), "3 files: a.txt, …");
+        assert_eq!(truncate_content(files, &ContentType::File, 50, &i18n), "3 files: a.txt, b.txt, c.txt");
+        assert_eq!(truncate_content(b"/srv/run.sh", &ContentType::File, 50, &i18n), "File: run.sh");
+        assert_eq!(truncate_content(b"", &ContentType::File, 50, &i18n), "File");
+    }
+
+    #[test]
+    fn test_clip_preview_masks_sensitive_clips() {
+        let i18n = TrayI18n::new("en");
+        let mut item = ClipItem::new(b"card 4111 1111 1111 1111".to_vec(), ContentType::Text);
+        item.sensitive = true;
+        assert_eq!(clip_preview(&item, 50, true, &i18n), "card ••••••••");
+        assert_eq!(clip_preview(&item, 50, false, &i18n), "card 4111 1111 1111 1111");
+
+        // A user-given title is shown as-is
+        item.title = Some("Company card".to_string());
+        assert_eq!(clip_preview(&item, 50, true, &i18n), "Company card");
+    }
 }
@@ -0,0 +1,197 @@
+// Windows clipboard formats arboard doesn't combine
+//
+// arboard sets one format per call and every call replaces the clipboard, so
+// multi-format clips (and RTF, which arboard doesn't know) are written here: the
+// clipboard is emptied once and the payload of every format added.
+use std::path::PathBuf;
+
+use clipboard_win::{raw, register_format, Clipboard};
+
+use crate::capture::parse_file_list;
+use crate::storage::{ClipFormat, ContentType};
+
+const CF_DIB: u32 = 8;
+const CF_UNICODETEXT: u32 = 13;
+const CF_HDROP: u32 = 15;
+
+const BITMAPINFOHEADER_SIZE: u32 = 40;
+const DROPFILES_SIZE: u32 = 20;
+
+const HTML_FORMAT: &str = "HTML Format";
+const RTF_FORMAT: &str = "Rich Text Format";
+const PNG_FORMAT: &str = "PNG";
+
+/// Formats password managers add to keep a copy out of clipboard managers and history
+const EXCLUDE_FROM_MONITORING_FORMAT: &str = "ExcludeClipboardContentFromMonitorProcessing";
+const VIEWER_IGNORE_FORMAT: &str = "Clipboard Viewer Ignore";
+/// A DWORD; 0 keeps the copy out of the clipboard history
+const CAN_INCLUDE_IN_HISTORY_FORMAT: &str = "CanIncludeInClipboardHistory";
+
+/// How often to retry opening the clipboard while another app holds it
+const OPEN_ATTEMPTS: usize = 10;
+
+fn err(e: impl std::fmt::Display) -> String {
+    e.to_string()
+}
+
+/// NUL-terminated UTF-16, as CF_UNICODETEXT and CF_HDROP store strings
+fn utf16z(text: &str) -> Vec<u8> {
+    text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+}
+
+/// CF_HTML: the fragment wrapped in a document, behind a header of byte offsets
+pub fn cf_html(fragment: &str) -> Vec<u8> {
+    const PREFIX: &str = "<html>\r\n<body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body>\r\n</html>";
+    // Every offset is written with 10 digits, so the header length is fixed
+    let header_len = format!(
+        "Version:0.9\r\nStartHTML:{0:010}\r\nEndHTML:{0:010}\r\nStartFragment:{0:010}\r\nEndFragment:{0:010}\r\n",
+        0
+    )
+    .len();
+
+    let start_fragment = header_len + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+    format!(
+        "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n{}{}{}",
+        header_len, end_html, start_fragment, end_fragment, PREFIX, fragment, SUFFIX
+    )
+    .into_bytes()
+}
+
+/// CF_DIB: a bottom-up 32-bit BGRA bitmap behind a BITMAPINFOHEADER, for apps
+/// that don't read the PNG format
+pub fn dib(png: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(png).ok()?.to_rgba8();
+    let (width, height) = image.dimensions();
+
+    let mut data = Vec::with_capacity(BITMAPINFOHEADER_SIZE as usize + image.len());
+    data.extend(BITMAPINFOHEADER_SIZE.to_le_bytes());
+    data.extend((width as i32).to_le_bytes());
+    // Positive height: rows are stored bottom-up
+    data.extend((height as i32).to_le_bytes());
+    data.extend(1u16.to_le_bytes()); // planes
+    data.extend(32u16.to_le_bytes()); // bits per pixel
+    data.extend(0u32.to_le_bytes()); // BI_RGB
+    data.extend((width * height * 4).to_le_bytes());
+    data.extend([0u8; 16]); // resolution, palette
+    for row in image.rows().rev() {
+        for pixel in row {
+            let [r, g, b, a] = pixel.0;
+            data.extend([b, g, r, a]);
+        }
+    }
+    Some(data)
+}
+
+/// CF_HDROP: a DROPFILES header followed by NUL-terminated UTF-16 paths and a final NUL
+pub fn hdrop(paths: &[PathBuf]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend(DROPFILES_SIZE.to_le_bytes()); // offset of the file list
+    data.extend([0u8; 8]); // drop point
+    data.extend(0u32.to_le_bytes()); // fNC
+    data.extend(1u32.to_le_bytes()); // fWide: UTF-16 paths
+    for path in paths {
+        data.extend(utf16z(&path.to_string_lossy()));
+    }
+    data.extend(0u16.to_le_bytes());
+    data
+}
+
+/// Clipboard format ids and payloads a clip format is offered as
+fn payloads(format: &ClipFormat) -> Vec<(u32, Vec<u8>)> {
+    let registered = |name: &str| register_format(name).map(|id| id.get());
+
+    match format.content_type {
+        ContentType::Text => vec![(CF_UNICODETEXT, utf16z(&String::from_utf8_lossy(&format.data)))],
+        ContentType::Html => registered(HTML_FORMAT)
+            .map(|id| (id, cf_html(&String::from_utf8_lossy(&format.data))))
+            .into_iter()
+            .collect(),
+        ContentType::Rtf => registered(RTF_FORMAT)
+            .map(|id| (id, [format.data.as_slice(), &[0]].concat()))
+            .into_iter()
+            .collect(),
+        // PNG first: apps that read it get transparency, the rest use the bitmap
+        ContentType::Image => registered(PNG_FORMAT)
+            .map(|id| (id, format.data.clone()))
+            .into_iter()
+            .chain(dib(&format.data).map(|dib| (CF_DIB, dib)))
+            .collect(),
+        ContentType::File => vec![(CF_HDROP, hdrop(&parse_file_list(&format.data)))],
+    }
+}
+
+/// Replace the clipboard with all `formats`, preferred first
+pub fn write_formats(formats: &[ClipFormat]) -> Result<(), String> {
+    let _clipboard = Clipboard::new_attempts(OPEN_ATTEMPTS).map_err(err)?;
+    raw::empty().map_err(err)?;
+    for format in formats {
+        for (id, data) in payloads(format) {
+            raw::set_without_clear(id, &data).map_err(err)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn read_rtf() -> Option<String> {
+    let format = register_format(RTF_FORMAT)?;
+    let _clipboard = Clipboard::new_attempts(OPEN_ATTEMPTS).ok()?;
+    let mut data = Vec::new();
+    raw::get_vec(format.get(), &mut data).ok()?;
+    Some(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())
+}
+
+/// Whether the clipboard owner marked its contents as not to be recorded
+pub fn is_concealed() -> bool {
+    let Ok(_clipboard) = Clipboard::new_attempts(OPEN_ATTEMPTS) else {
+        return false;
+    };
+    let available = |name: &str| register_format(name).is_some_and(|id| raw::is_format_avail(id.get()));
+    if available(EXCLUDE_FROM_MONITORING_FORMAT) || available(VIEWER_IGNORE_FORMAT) {
+        return true;
+    }
+
+    let mut data = Vec::new();
+    register_format(CAN_INCLUDE_IN_HISTORY_FORMAT)
+        .is_some_and(|id| raw::get_vec(id.get(), &mut data).is_ok() && data.starts_with(&0u32.to_le_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cf_html_offsets() {
+        let data = String::from_utf8(cf_html("<b>hi</b>")).unwrap();
+        let offset = |name: &str| -> usize {
+            let start = data.find(name).unwrap() + name.len();
+            data[start..start + 10].parse().unwrap()
+        };
+
+        assert!(data[offset("StartHTML:")..].starts_with("<html>"));
+        assert_eq!(&data[offset("StartFragment:")..offset("EndFragment:")], "<b>hi</b>");
+        assert_eq!(offset("EndHTML:"), data.len());
+    }
+
+    #[test]
+    fn test_dib_and_hdrop_layout() {
+        // 1x2 image: red on top, blue below
+        let mut png = Vec::new();
+        image::RgbaImage::from_raw(1, 2, vec![255, 0, 0, 255, 0, 0, 255, 255])
+            .unwrap()
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let dib = dib(&png).unwrap();
+        assert_eq!(dib.len(), 40 + 8);
+        assert_eq!(&dib[4..12], &[1, 0, 0, 0, 2, 0, 0, 0]);
+        // Bottom-up BGRA: the blue row comes first
+        assert_eq!(&dib[40..], &[255, 0, 0, 255, 0, 0, 255, 255]);
+
+        let hdrop = hdrop(&[PathBuf::from("C:\\a"), PathBuf::from("b")]);
+        assert_eq!(&hdrop[..4], &[20, 0, 0, 0]);
+        let paths: Vec<u16> = hdrop[20..].chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        assert_eq!(String::from_utf16(&paths).unwrap(), "C:\\a\0b\0\0");
+    }
+}
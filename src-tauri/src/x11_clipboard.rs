@@ -0,0 +1,429 @@
+// Direct X11 selection access for clipboard flavours arboard doesn't handle
+//
+// Reading converts the CLIPBOARD (or PRIMARY) selection to a named target (`text/rtf`, ...).
+// Writing takes ownership of the selection on a background thread that answers
+// requests for a fixed set of targets until another app copies something.
+// Transfers larger than one request (INCR) are not supported. The active window's
+// app is looked up here too, as the source of captured clips.
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, SelectionNotifyEvent,
+    SelectionRequestEvent, Window, WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+use crate::capture::parse_file_list;
+use crate::source_app::SourceApp;
+use crate::storage::{ClipFormat, ContentType, Selection};
+
+/// How long to wait for the selection owner to answer a conversion
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Targets plain text is offered under
+const TEXT_TARGETS: &[&str] = &["UTF8_STRING", "text/plain;charset=utf-8", "text/plain", "STRING", "TEXT"];
+
+/// Targets apps offer RTF under, preferred first
+pub const RTF_TARGETS: &[&str] = &["text/rtf", "application/rtf", "text/richtext"];
+
+/// Target KDE and password managers like KeePassXC offer with secrets; `secret`
+/// asks clipboard managers not to record the copy
+const PASSWORD_MANAGER_HINT: &str = "x-kde-passwordManagerHint";
+
+/// Characters left as-is in `file://` URIs
+const URI_PATH: &AsciiSet = &NON_ALPHANUMERIC.remove(b'/').remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+/// Property on our window that converted selections are written to
+const TRANSFER_PROPERTY: &str = "CLIPMAN_SELECTION";
+
+fn err(e: impl std::fmt::Display) -> String {
+    e.to_string()
+}
+
+struct Session {
+    conn: RustConnection,
+    root: Window,
+    window: Window,
+}
+
+impl Session {
+    fn connect() -> Result<Self, String> {
+        let (conn, screen) = x11rb::connect(None).map_err(err)?;
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id().map_err(err)?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )
+        .map_err(err)?;
+        conn.flush().map_err(err)?;
+        Ok(Self { conn, root, window })
+    }
+
+    fn atom(&self, name: &str) -> Result<Atom, String> {
+        Ok(self.conn.intern_atom(false, name.as_bytes()).map_err(err)?.reply().map_err(err)?.atom)
+    }
+}
+
+fn selection_name(selection: Selection) -> &'static str {
+    match selection {
+        Selection::Clipboard => "CLIPBOARD",
+        Selection::Primary => "PRIMARY",
+    }
+}
+
+/// Read the CLIPBOARD selection converted to `target`.
+/// `None` if there is no owner or it doesn't offer the target.
+pub fn read(target: &str) -> Result<Option<Vec<u8>>, String> {
+    read_from(Selection::Clipboard, target)
+}
+
+/// Read `selection` converted to `target`, like `read`
+fn read_from(selection: Selection, target: &str) -> Result<Option<Vec<u8>>, String> {
+    let session = Session::connect()?;
+    let conn = &session.conn;
+    let selection = session.atom(selection_name(selection))?;
+    let target = session.atom(target)?;
+    let property = session.atom(TRANSFER_PROPERTY)?;
+    let incr = session.atom("INCR")?;
+
+    if conn.get_selection_owner(selection).map_err(err)?.reply().map_err(err)?.owner == NONE {
+        return Ok(None);
+    }
+
+    conn.convert_selection(session.window, selection, target, property, CURRENT_TIME).map_err(err)?;
+    conn.flush().map_err(err)?;
+
+    let deadline = Instant::now() + READ_TIMEOUT;
+    while Instant::now() < deadline {
+        match conn.poll_for_event().map_err(err)? {
+            Some(Event::SelectionNotify(event)) if event.requestor == session.window => {
+                if event.property == NONE {
+                    return Ok(None);
+                }
+                let reply = conn
+                    .get_property(true, session.window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+                    .map_err(err)?
+                    .reply()
+                    .map_err(err)?;
+                // Incremental transfers are only used for very large payloads
+                if reply.type_ == incr {
+                    log::warn!("⚠️ Skipping incremental selection transfer");
+                    return Ok(None);
+                }
+                return Ok(Some(reply.value));
+            }
+            Some(_) => {}
+            None => std::thread::sleep(Duration::from_millis(5)),
+        }
+    }
+
+    log::debug!("Selection owner did not answer in time");
+    Ok(None)
+}
+
+/// Read the CLIPBOARD selection as UTF-8 text for the first offered target
+pub fn read_string(targets: &[&str]) -> Result<Option<String>, String> {
+    for target in targets {
+        if let Some(data) = read(target)? {
+            // Windows-style writers include the terminating NUL
+            let data = data.strip_suffix(&[0]).unwrap_or(&data);
+            return Ok(Some(String::from_utf8_lossy(data).into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether the owner of `selection` marked its contents as a secret not to be recorded
+pub fn is_concealed(selection: Selection) -> Result<bool, String> {
+    Ok(read_from(selection, PASSWORD_MANAGER_HINT)?.is_some_and(|hint| hint.trim_ascii() == b"secret"))
+}
+
+/// The app of the window the window manager marks active (`_NET_ACTIVE_WINDOW`): its
+/// WM_CLASS class and `_NET_WM_PID`. Windows without WM_CLASS are named after their
+/// process. `None` without a window manager that sets the property.
+pub fn active_window_app() -> Result<Option<SourceApp>, String> {
+    let session = Session::connect()?;
+    let conn = &session.conn;
+    let active_atom = session.atom("_NET_ACTIVE_WINDOW")?;
+    let pid_atom = session.atom("_NET_WM_PID")?;
+
+    let property = |window: Window, property: Atom, type_: Atom| -> Result<Vec<u8>, String> {
+        let reply = conn.get_property(false, window, property, type_, 0, 1024).map_err(err)?.reply().map_err(err)?;
+        Ok(reply.value)
+    };
+    let cardinal = |data: Vec<u8>| data.get(..4).and_then(|bytes| bytes.try_into().ok()).map(u32::from_ne_bytes);
+
+    let Some(window) = cardinal(property(session.root, active_atom, AtomEnum::WINDOW.into())?).filter(|&window| window != NONE)
+    else {
+        return Ok(None);
+    };
+
+    // WM_CLASS holds the instance and class names, each NUL-terminated
+    let class = property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+    let mut names = class.split(|&byte| byte == 0).filter(|name| !name.is_empty());
+    let instance = names.next();
+    let name = names.next().or(instance).map(|name| String::from_utf8_lossy(name).into_owned());
+    let pid = cardinal(property(window, pid_atom, AtomEnum::CARDINAL.into())?);
+
+    let name = name.or_else(|| {
+        let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid?)).ok()?;
+        Some(comm.trim().to_string())
+    });
+    Ok(name.map(|name| SourceApp { name, pid }))
+}
+
+/// `(target, data)` pairs that offer plain text under the usual text targets
+pub fn text_formats(text: &str) -> Vec<(String, Vec<u8>)> {
+    TEXT_TARGETS.iter().map(|target| (target.to_string(), text.as_bytes().to_vec())).collect()
+}
+
+fn file_uri(path: &Path) -> String {
+    format!("file://{}", utf8_percent_encode(&path.to_string_lossy(), URI_PATH))
+}
+
+/// `(target, data)` pairs offering each format under the targets apps look for
+pub fn format_targets(formats: &[ClipFormat]) -> Vec<(String, Vec<u8>)> {
+    let mut targets = Vec::new();
+    for format in formats {
+        match format.content_type {
+            ContentType::Text => targets.extend(text_formats(&String::from_utf8_lossy(&format.data))),
+            ContentType::Html => targets.push(("text/html".to_string(), format.data.clone())),
+            ContentType::Rtf => targets.extend(RTF_TARGETS.iter().map(|target| (target.to_string(), format.data.clone()))),
+            ContentType::Image => targets.push(("image/png".to_string(), format.data.clone())),
+            ContentType::File => {
+                let uris: Vec<String> = parse_file_list(&format.data).iter().map(|path| file_uri(path)).collect();
+                targets.push(("text/uri-list".to_string(), uris.join("\r\n").into_bytes()));
+                // GNOME and KDE file managers paste files from this target
+                targets.push(("x-special/gnome-copied-files".to_string(), format!("copy\n{}", uris.join("\n")).into_bytes()));
+            }
+        }
+    }
+    targets
+}
+
+/// Answer one selection request. Returns the property the data was written to,
+/// or NONE to refuse.
+fn answer(
+    session: &Session,
+    request: &SelectionRequestEvent,
+    targets_atom: Atom,
+    formats: &[(Atom, Vec<u8>)],
+) -> Result<Atom, String> {
+    let conn = &session.conn;
+    // Obsolete clients pass no property and expect the target to be used
+    let property = if request.property == NONE { request.target } else { request.property };
+
+    if request.target == targets_atom {
+        let mut atoms: Vec<Atom> = formats.iter().map(|(atom, _)| *atom).collect();
+        atoms.push(targets_atom);
+        conn.change_property32(PropMode::REPLACE, request.requestor, property, AtomEnum::ATOM, &atoms)
+            .map_err(err)?;
+        return Ok(property);
+    }
+
+    match formats.iter().find(|(atom, _)| *atom == request.target) {
+        Some((atom, data)) if data.len() + 64 <= conn.maximum_request_bytes() => {
+            conn.change_property8(PropMode::REPLACE, request.requestor, property, *atom, data).map_err(err)?;
+            Ok(property)
+        }
+        Some(_) => {
+            log::warn!("⚠️ Selection data too large to serve without INCR");
+            Ok(NONE)
+        }
+        None => Ok(NONE),
+    }
+}
+
+/// Own the CLIPBOARD selection and serve `formats` (`(target, data)` pairs) from a
+/// background thread until another client takes the selection over.
+pub fn serve(formats: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    let session = Session::connect()?;
+    let selection = session.atom("CLIPBOARD")?;
+    let targets_atom = session.atom("TARGETS")?;
+    let formats = formats
+        .into_iter()
+        .map(|(target, data)| Ok((session.atom(&target)?, data)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let conn = &session.conn;
+    conn.set_selection_owner(session.window, selection, CURRENT_TIME).map_err(err)?;
+    conn.flush().map_err(err)?;
+    if conn.get_selection_owner(selection).map_err(err)?.reply().map_err(err)?.owner != session.window {
+        return Err("Failed to take ownership of the clipboard".to_string());
+    }
+
+    std::thread::spawn(move || {
+        let conn = &session.conn;
+        loop {
+            let event = match conn.wait_for_event() {
+                Ok(event) => event,
+                Err(e) => {
+                    log::error!("X11 selection connection failed: {}", e);
+                    return;
+                }
+            };
+
+            match event {
+                Event::SelectionRequest(request) => {
+                    let property = answer(&session, &request, targets_atom, &formats).unwrap_or_else(|e| {
+                        log::warn!("⚠️ Failed to answer selection request: {}", e);
+                        NONE
+                    });
+                    let notify = SelectionNotifyEvent {
+                        response_type: SELECTION_NOTIFY_EVENT,
+                        sequence: 0,
+                        time: request.time,
+                        requestor: request.requestor,
+                        selection: request.selection,
+                        target: request.target,
+                        property,
+                    };
+                    conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify).ok();
+                    conn.flush().ok();
+                }
+                Event::SelectionClear(_) => {
+                    log::debug!("Clipboard ownership passed to another client");
+                    return;
+                }
+                _ => {}
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// The tests that talk to an X server are ignored by default, since they take
+/// clipboard ownership and change window properties on whatever display `DISPLAY`
+/// points to. Run them on a virtual one: `xvfb-run cargo test -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Serializes the X server tests, which share the CLIPBOARD selection
+    static X11_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lock_x11() -> std::sync::MutexGuard<'static, ()> {
+        X11_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[test]
+    fn test_format_targets() {
+        let formats = [
+            ClipFormat { content_type: ContentType::File, data: "/home/me/a b.txt\n/tmp/ü.png".as_bytes().to_vec() },
+            ClipFormat { content_type: ContentType::Text, data: b"notes".to_vec() },
+        ];
+        let targets = format_targets(&formats);
+        let target = |name: &str| targets.iter().find(|(target, _)| target == name).map(|(_, data)| data.as_slice());
+
+        assert_eq!(target("text/uri-list"), Some(&b"file:///home/me/a%20b.txt\r\nfile:///tmp/%C3%BC.png"[..]));
+        assert_eq!(target("x-special/gnome-copied-files"), Some(&b"copy\nfile:///home/me/a%20b.txt\nfile:///tmp/%C3%BC.png"[..]));
+        assert_eq!(target("UTF8_STRING"), Some(&b"notes"[..]));
+        assert_eq!(target("text/html"), None);
+    }
+
+    #[test]
+    #[ignore = "needs an X server: xvfb-run cargo test -- --ignored"]
+    fn test_password_manager_hint() {
+        let _x11 = lock_x11();
+        let mut secret = text_formats("hunter2");
+        secret.push((PASSWORD_MANAGER_HINT.to_string(), b"secret".to_vec()));
+        serve(secret).unwrap();
+        assert!(is_concealed(Selection::Clipboard).unwrap());
+        assert_eq!(read_string(&["UTF8_STRING"]).unwrap().as_deref(), Some("hunter2"));
+
+        serve(text_formats("notes")).unwrap();
+        assert!(!is_concealed(Selection::Clipboard).unwrap());
+    }
+
+    #[test]
+    #[ignore = "needs an X server: xvfb-run cargo test -- --ignored"]
+    fn test_clipboard_outlives_its_owner() {
+        let _x11 = lock_x11();
+
+        // An app copies, ClipMan takes the clipboard over, then the app exits
+        let app = Session::connect().unwrap();
+        let clipboard = app.atom("CLIPBOARD").unwrap();
+        app.conn.set_selection_owner(app.window, clipboard, CURRENT_TIME).unwrap();
+        app.conn.get_input_focus().unwrap().reply().unwrap();
+        let formats = [
+            ClipFormat { content_type: ContentType::Html, data: b"<b>notes</b>".to_vec() },
+            ClipFormat { content_type: ContentType::Text, data: b"notes".to_vec() },
+        ];
+        serve(format_targets(&formats)).unwrap();
+        drop(app);
+
+        assert_eq!(read_string(&["text/html"]).unwrap().as_deref(), Some("<b>notes</b>"));
+        assert_eq!(read_string(&["UTF8_STRING"]).unwrap().as_deref(), Some("notes"));
+    }
+
+    /// Puts the root window's `_NET_ACTIVE_WINDOW` back as it was when dropped
+    struct RestoreActiveWindow<'a> {
+        session: &'a Session,
+        atom: Atom,
+        previous: Option<Vec<u32>>,
+    }
+
+    impl<'a> RestoreActiveWindow<'a> {
+        fn save(session: &'a Session, atom: Atom) -> Self {
+            let reply = session.conn.get_property(false, session.root, atom, AtomEnum::WINDOW, 0, 1).unwrap().reply().unwrap();
+            let previous = (reply.type_ != NONE).then(|| reply.value32().into_iter().flatten().collect());
+            Self { session, atom, previous }
+        }
+    }
+
+    impl Drop for RestoreActiveWindow<'_> {
+        fn drop(&mut self) {
+            let conn = &self.session.conn;
+            match &self.previous {
+                Some(windows) => conn.change_property32(PropMode::REPLACE, self.session.root, self.atom, AtomEnum::WINDOW, windows).ok(),
+                None => conn.delete_property(self.session.root, self.atom).ok(),
+            };
+            conn.flush().ok();
+        }
+    }
+
+    #[test]
+    #[ignore = "needs an X server: xvfb-run cargo test -- --ignored"]
+    fn test_active_window_app() {
+        let _x11 = lock_x11();
+
+        // Stand in for the window manager and mark a window of ours active
+        let session = Session::connect().unwrap();
+        let conn = &session.conn;
+        let pid = std::process::id();
+        let pid_atom = session.atom("_NET_WM_PID").unwrap();
+        let active_atom = session.atom("_NET_ACTIVE_WINDOW").unwrap();
+        let _restore = RestoreActiveWindow::save(&session, active_atom);
+        conn.change_property8(PropMode::REPLACE, session.window, AtomEnum::WM_CLASS, AtomEnum::STRING, b"keepassxc\0KeePassXC\0")
+            .unwrap();
+        conn.change_property32(PropMode::REPLACE, session.window, pid_atom, AtomEnum::CARDINAL, &[pid]).unwrap();
+        conn.change_property32(PropMode::REPLACE, session.root, active_atom, AtomEnum::WINDOW, &[session.window]).unwrap();
+        // A round trip makes sure the server has the properties before they are read
+        conn.get_input_focus().unwrap().reply().unwrap();
+        assert_eq!(active_window_app().unwrap(), Some(SourceApp { name: "KeePassXC".to_string(), pid: Some(pid) }));
+
+        // Without WM_CLASS the process names the app
+        conn.delete_property(session.window, AtomEnum::WM_CLASS.into()).unwrap();
+        conn.get_input_focus().unwrap().reply().unwrap();
+        let comm = std::fs::read_to_string("/proc/self/comm").unwrap();
+        assert_eq!(active_window_app().unwrap().map(|app| app.name), Some(comm.trim().to_string()));
+    }
+}
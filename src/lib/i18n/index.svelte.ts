@@ -73,6 +73,29 @@ export interface Translations {
   maxHistoryItemsDesc: string;
   storeOriginalImage: string;
   storeOriginalImageDesc: string;
+  detectSensitive: string;
+  detectSensitiveDesc: string;
+  maskSensitive: string;
+  maskSensitiveDesc: string;
+  showSensitive: string;
+  hideSensitive: string;
+  excludedApps: string;
+  excludedAppsDesc: string;
+  allowedApps: string;
+  allowedAppsDesc: string;
+  copiedFrom: string;
+  selectedText: string;
+  selectedTextDesc: string;
+  capturePrimary: string;
+  capturePrimaryDesc: string;
+  syncSelections: string;
+  syncSelectionsDesc: string;
+  selectionDebounce: string;
+  selectionDebounceDesc: string;
+  persistClipboard: string;
+  persistClipboardDesc: string;
+  openLink: string;
+  sendEmail: string;
   
   // Appearance settings
   themeMode: string;
@@ -217,6 +240,29 @@ const zh: Translations = {
   maxHistoryItemsDesc: '保留的最大历史记录数量',
   storeOriginalImage: '存储原图',
   storeOriginalImageDesc: '保存原始图片而非缩略图（占用更多空间）',
+  detectSensitive: '识别敏感内容',
+  detectSensitiveDesc: '将银行卡号、访问令牌、私钥和密码标记为敏感',
+  maskSensitive: '隐藏敏感内容',
+  maskSensitiveDesc: '在托盘和窗口中遮盖敏感内容，点击后显示',
+  showSensitive: '显示内容',
+  hideSensitive: '隐藏内容',
+  excludedApps: '忽略的应用',
+  excludedAppsDesc: '不记录从这些应用复制的内容（窗口类名或程序名，用逗号分隔）',
+  allowedApps: '仅记录的应用',
+  allowedAppsDesc: '只记录从这些应用复制的内容，留空则记录所有应用',
+  copiedFrom: '复制自',
+  selectedText: '选中',
+  selectedTextDesc: '选中的文本（PRIMARY 选区），未复制',
+  capturePrimary: '记录选中的文本',
+  capturePrimaryDesc: '同时记录选中即可中键粘贴的文本（PRIMARY 选区，仅 Linux）',
+  syncSelections: '同步剪贴板和选区',
+  syncSelectionsDesc: '选中的文本会复制到剪贴板，复制的文本也会被选中（仅 Linux）',
+  selectionDebounce: '选区稳定时间',
+  selectionDebounceDesc: '选中的文本保持不变这么久后才会被记录或同步，拖动选择时不会刷屏',
+  persistClipboard: '应用退出后保留剪贴板',
+  persistClipboardDesc: '由 ClipMan 提供复制的内容，复制它的应用退出后仍可粘贴；只保留 ClipMan 能记录的格式（仅 Linux X11；Wayland 下只对通过 XWayland 运行的应用有效）',
+  openLink: '打开链接',
+  sendEmail: '发送邮件',
   
   // Appearance settings
   themeMode: '主题模式',
@@ -361,6 +407,29 @@ const en: Translations = {
   maxHistoryItemsDesc: 'Maximum number of history items to keep',
   storeOriginalImage: 'Store original images',
   storeOriginalImageDesc: 'Save original images instead of thumbnails (uses more space)',
+  detectSensitive: 'Detect sensitive content',
+  detectSensitiveDesc: 'Flag card numbers, access tokens, private keys and passwords as sensitive',
+  maskSensitive: 'Mask sensitive content',
+  maskSensitiveDesc: 'Hide sensitive content in the tray and window until shown',
+  showSensitive: 'Show content',
+  hideSensitive: 'Hide content',
+  excludedApps: 'Ignored apps',
+  excludedAppsDesc: 'Never record copies from these apps (window class or program name, comma-separated)',
+  allowedApps: 'Only record apps',
+  allowedAppsDesc: 'Only record copies from these apps; leave empty to record all apps',
+  copiedFrom: 'Copied from',
+  selectedText: 'Selected',
+  selectedTextDesc: 'Selected text (the PRIMARY selection), not copied',
+  capturePrimary: 'Record selected text',
+  capturePrimaryDesc: 'Also record text that is selected for middle-click pasting (the PRIMARY selection, Linux only)',
+  syncSelections: 'Sync clipboard and selection',
+  syncSelectionsDesc: 'Selected text is copied to the clipboard and copied text is selected (Linux only)',
+  selectionDebounce: 'Selection settle time',
+  selectionDebounceDesc: 'A selection is only recorded or synced once it stays unchanged this long, so dragging does not flood history',
+  persistClipboard: 'Keep clipboard when apps exit',
+  persistClipboardDesc: 'ClipMan serves what you copy, so it can still be pasted after the app it came from exits. Only the formats ClipMan records are kept (Linux on X11; on Wayland only for apps running under XWayland)',
+  openLink: 'Open link',
+  sendEmail: 'Send email',
   
   // Appearance settings
   themeMode: 'Theme',
@@ -2,7 +2,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { toastStore } from './toast.svelte';
 import { i18n } from '$lib/i18n';
-import type { ClipItem } from '$lib/types';
+import type { ClipItem, ClipRevision, FieldMatch, SavedSearch, SearchResult, SortMode } from '$lib/types';
 
 // Re-export type for convenience
 export type { ClipItem } from '$lib/types';
@@ -10,8 +10,19 @@ export type { ClipItem } from '$lib/types';
 class ClipboardStore {
   items = $state<ClipItem[]>([]);
   searchQuery = $state('');
+  // Relevance ranks search results; without a query it lists newest first
+  sortMode = $state<SortMode>('relevance');
+  // Highlight ranges of the current search results, by clip id
+  matches = $state<Record<string, FieldMatch[]>>({});
+  // Syntax error of the current search query, e.g. "Unterminated quote (at position 4)"
+  searchError = $state<string | null>(null);
+  savedSearches = $state<SavedSearch[]>([]);
+  // Saved search currently shown instead of the history, if any
+  activeSavedSearchId = $state<string | null>(null);
   isLoading = $state(false);
   maxHistoryItems = $state(100); // Default limit
+  // Hide the content of sensitive clips until shown
+  maskSensitive = $state(true);
   private unlisten?: () => void;
 
   // Derived state: pinned items sorted by pin order
@@ -33,8 +44,9 @@ class ClipboardStore {
   async initialize() {
     // Load settings first to get maxHistoryItems
     try {
-      const settings = await invoke<{ maxHistoryItems: number }>('get_settings');
+      const settings = await invoke<{ maxHistoryItems: number; maskSensitive: boolean }>('get_settings');
       this.maxHistoryItems = settings.maxHistoryItems;
+      this.maskSensitive = settings.maskSensitive;
     } catch (e) {
       console.error('Failed to load settings:', e);
     }
@@ -89,10 +101,37 @@ class ClipboardStore {
       await this.loadHistory();
     });
 
-    // Store both unlisten functions
+    // Listen for in-place edits (content changes, reverts)
+    const unlistenClipUpdated = await listen<ClipItem>('clip-updated', (event) => {
+      const updated = event.payload;
+      this.items = this.items.map((item) => (item.id === updated.id ? updated : item));
+    });
+
+    // Listen for clips removed by the backend (expired or burned after paste)
+    const unlistenClipsExpired = await listen<string[]>('clips-expired', (event) => {
+      const expired = new Set(event.payload);
+      this.items = this.items.filter((item) => !expired.has(item.id));
+    });
+
+    // Refresh the open smart collection when a new capture matches it
+    const unlistenSavedSearchMatched = await listen<{ clipId: string; searchIds: string[] }>(
+      'saved-search-matched',
+      async (event) => {
+        if (this.activeSavedSearchId && event.payload.searchIds.includes(this.activeSavedSearchId)) {
+          await this.runSavedSearch(this.activeSavedSearchId);
+        }
+      }
+    );
+
+    await this.loadSavedSearches();
+
+    // Store all unlisten functions
     this.unlisten = () => {
       unlistenClipboard();
       unlistenHistoryCleared();
+      unlistenClipUpdated();
+      unlistenClipsExpired();
+      unlistenSavedSearchMatched();
     };
   }
 
@@ -107,6 +146,7 @@ class ClipboardStore {
       console.log('[INFO] Loading clipboard history...');
       const history = await invoke<ClipItem[]>('get_clipboard_history', {
         limit: 100,
+        sort: this.sortMode,
       });
       console.log(`[SUCCESS] Loaded ${history.length} clipboard items`);
 
@@ -133,6 +173,9 @@ class ClipboardStore {
 
   async search(query: string) {
     this.searchQuery = query;
+    this.searchError = null;
+    this.activeSavedSearchId = null;
+    this.matches = {};
 
     // When query is empty, reload full history
     if (!query) {
@@ -143,15 +186,68 @@ class ClipboardStore {
     // Use full-text search for complex queries
     this.isLoading = true;
     try {
-      const results = await invoke<ClipItem[]>('search_clips', { query });
-      this.items = results;
+      const results = await invoke<SearchResult[]>('search_clips', { query, sort: this.sortMode });
+      this.items = results.map((result) => result.item);
+      this.matches = Object.fromEntries(results.map((result) => [result.item.id, result.matches]));
     } catch (error) {
       console.error('Search failed:', error);
+      this.searchError = String(error);
     } finally {
       this.isLoading = false;
     }
   }
 
+  async loadSavedSearches() {
+    try {
+      this.savedSearches = await invoke<SavedSearch[]>('get_saved_searches');
+    } catch (error) {
+      console.error('Failed to load saved searches:', error);
+    }
+  }
+
+  async saveSearch(name: string, query: string, showInTray: boolean, id: string | null = null) {
+    try {
+      await invoke<SavedSearch>('save_search', { id, name, query, sort: this.sortMode, showInTray });
+      await this.loadSavedSearches();
+    } catch (error) {
+      console.error('Failed to save search:', error);
+      throw error;
+    }
+  }
+
+  async deleteSavedSearch(id: string) {
+    try {
+      await invoke('delete_saved_search', { id });
+      if (this.activeSavedSearchId === id) {
+        this.activeSavedSearchId = null;
+        await this.loadHistory();
+      }
+      await this.loadSavedSearches();
+    } catch (error) {
+      console.error('Failed to delete saved search:', error);
+      throw error;
+    }
+  }
+
+  async runSavedSearch(id: string) {
+    this.isLoading = true;
+    try {
+      const results = await invoke<SearchResult[]>('run_saved_search', { id });
+      this.activeSavedSearchId = id;
+      this.items = results.map((result) => result.item);
+      this.matches = Object.fromEntries(results.map((result) => [result.item.id, result.matches]));
+    } catch (error) {
+      console.error('Failed to run saved search:', error);
+    } finally {
+      this.isLoading = false;
+    }
+  }
+
+  async setSortMode(mode: SortMode) {
+    this.sortMode = mode;
+    await this.search(this.searchQuery);
+  }
+
   async clearNonPinned() {
     try {
       await invoke('clear_non_pinned_history');
@@ -178,6 +274,100 @@ class ClipboardStore {
     }
   }
 
+  async movePin(id: string, newIndex: number) {
+    try {
+      await invoke('move_pin', { id, newIndex });
+      await this.loadHistory();
+    } catch (error) {
+      console.error('Failed to move pin:', error);
+    }
+  }
+
+  async assignPinGroup(id: string, groupId: string | null) {
+    try {
+      await invoke('assign_pin_group', { id, groupId });
+      await this.loadHistory();
+    } catch (error) {
+      console.error('Failed to assign pin group:', error);
+    }
+  }
+
+  async updateContent(id: string, content: string) {
+    try {
+      await invoke<ClipItem>('update_clip_content', { id, content });
+    } catch (error) {
+      console.error('Failed to update clip content:', error);
+      throw error;
+    }
+  }
+
+  async updateDetails(id: string, title: string | null, note: string | null) {
+    try {
+      await invoke<ClipItem>('update_clip_details', { id, title, note });
+    } catch (error) {
+      console.error('Failed to update clip details:', error);
+      throw error;
+    }
+  }
+
+  async setTags(id: string, tags: string[]) {
+    try {
+      await invoke<ClipItem>('set_clip_tags', { id, tags });
+    } catch (error) {
+      console.error('Failed to update clip tags:', error);
+      throw error;
+    }
+  }
+
+  async setExpiry(id: string, expiresAt: number | null) {
+    try {
+      await invoke<ClipItem>('set_clip_expiry', { id, expiresAt });
+    } catch (error) {
+      console.error('Failed to set clip expiry:', error);
+      throw error;
+    }
+  }
+
+  async setSensitive(id: string, sensitive: boolean) {
+    try {
+      await invoke<ClipItem>('set_clip_sensitive', { id, sensitive });
+    } catch (error) {
+      console.error('Failed to set sensitive flag:', error);
+      throw error;
+    }
+  }
+
+  async setClearAfter(id: string, clearAfterSecs: number | null) {
+    try {
+      await invoke<ClipItem>('set_clip_clear_after', { id, clearAfterSecs });
+    } catch (error) {
+      console.error('Failed to set clipboard clearing delay:', error);
+      throw error;
+    }
+  }
+
+  async setPasteLimit(id: string, maxPastes: number | null) {
+    try {
+      await invoke<ClipItem>('set_clip_paste_limit', { id, maxPastes });
+    } catch (error) {
+      console.error('Failed to set paste limit:', error);
+      throw error;
+    }
+  }
+
+  async listRevisions(id: string): Promise<ClipRevision[]> {
+    return invoke<ClipRevision[]>('list_revisions', { id });
+  }
+
+  async revertRevision(id: string, revisionId: number) {
+    try {
+      await invoke<ClipItem>('revert_revision', { id, revisionId });
+    } catch (error) {
+      console.error('Failed to revert revision:', error);
+      throw error;
+    }
+  }
+
   async deleteItem(id: string) {
     try {
       await invoke('delete_clip', { id });
@@ -5,6 +5,18 @@
  */
 export type ContentType = 'text' | 'image' | 'file' | 'html' | 'rtf';
 
+/** What a text clip holds, labelled at capture; filter with `kind:` in search */
+export type TextKind =
+  | 'url'
+  | 'email'
+  | 'phone'
+  | 'color'
+  | 'json'
+  | 'code'
+  | 'path'
+  | 'number'
+  | 'prose';
+
 /**
  * Clipboard item from backend
  */
@@ -17,8 +29,176 @@ export interface ClipItem {
   timestamp: number;
   isPinned: boolean;
   pinOrder: number | null;
+  /** Pin group this item belongs to, if any */
+  pinGroupId: string | null;
+  /** Optional user-given title, shown instead of the content preview */
+  title: string | null;
+  /** Optional free-form note */
+  note: string | null;
+  /** Unix timestamp in seconds after which the clip is deleted */
+  expiresAt: number | null;
+  /** Pastes left before the clip is deleted */
+  pastesRemaining: number | null;
+  /** Times the clip was copied back from history */
+  copyCount: number;
+  /** Unix timestamp in seconds of the last copy-back */
+  lastUsedAt: number | null;
+  tags: string[];
+  /** Plain-text alternative captured with rich text (HTML, RTF) clips */
+  plainText: string | null;
+  /** Holds a secret (card number, token, key, password); masked until shown */
+  sensitive: boolean;
+  /** Seconds after a paste from history at which the clipboard is cleared again */
+  clearAfterSecs: number | null;
+  /** App the clip was copied from (window class, executable or app name) */
+  sourceApp: string | null;
+  sourcePid: number | null;
+  /** What the text holds; null for images and files */
+  textKind: TextKind | null;
+  /** Copied to the clipboard, or only selected (the Linux PRIMARY selection) */
+  selection: Selection;
+}
+
+/**
+ * X11 selection a clip was captured from; filter with `selection:` in search
+ */
+export type Selection = 'clipboard' | 'primary';
+
+/**
+ * Ordering of history, search results and the tray
+ */
+export type SortMode = 'recent' | 'mostUsed' | 'frecency' | 'size' | 'type' | 'relevance';
+
+/**
+ * Part of a clip a search matched. `content` is the plain-text rendering for HTML and RTF clips.
+ */
+export type SearchField = 'content' | 'fileName' | 'path' | 'title' | 'note';
+
+/**
+ * Highlighted character range in one field of a search result
+ */
+export interface FieldMatch {
+  field: SearchField;
+  start: number;
+  end: number;
+}
+
+/**
+ * Search hit returned by search_clips
+ */
+export interface SearchResult {
+  item: ClipItem;
+  score: number;
+  matches: FieldMatch[];
+  matchedFields: SearchField[];
+}
+
+/**
+ * Named query shown as a smart collection and optionally as a tray submenu
+ */
+export interface SavedSearch {
+  id: string;
+  name: string;
+  query: string;
+  sort: SortMode;
+  showInTray: boolean;
+  /** Unix timestamp in seconds */
+  createdAt: number;
+}
+
+/**
+ * Named group of pinned items
+ */
+export interface PinGroup {
+  id: string;
+  name: string;
+  position: number;
+}
+
+/**
+ * Previous version of an edited clip
+ */
+export interface ClipRevision {
+  id: number;
+  clipId: string;
+  content: string;
+  /** Unix timestamp in seconds */
+  createdAt: number;
+}
+
+/**
+ * Cleanup applied to captured text before it is stored
+ */
+export type TransformKind =
+  | { kind: 'trimWhitespace' }
+  | { kind: 'normalizeLineEndings' }
+  | { kind: 'stripTrackingParams' }
+  | { kind: 'stripShellPrompt' }
+  | { kind: 'replace'; pattern: string; replacement: string };
+
+/**
+ * Toggleable transform rule; rules run in order
+ */
+export type TransformRule = TransformKind & {
+  name: string;
+  enabled: boolean;
+};
+
+/**
+ * Result of `preview_transforms`
+ */
+export interface TransformPreview {
+  text: string;
+  /** Names of the rules that changed the text */
+  applied: string[];
 }
 
+/**
+ * How a capture rule finds secrets in captured text
+ */
+export type CaptureRuleMatcher =
+  | { matcher: 'regex'; pattern: string }
+  | { matcher: 'prefix'; prefix: string }
+  | { matcher: 'entropy'; minBitsPerChar: number; minLength: number };
+
+/**
+ * What happens to a clip a capture rule matches
+ */
+export type CaptureRuleAction =
+  | { action: 'skip' }
+  | { action: 'redact' }
+  | { action: 'expire'; afterSecs: number | null; maxPastes: number | null };
+
+/**
+ * Rule keeping secrets out of the history; every enabled matching rule applies
+ */
+export type CaptureRule = CaptureRuleMatcher & CaptureRuleAction & {
+  name: string;
+  enabled: boolean;
+  /** Clear the clipboard this many seconds after a matching clip is pasted from history */
+  clearAfterSecs: number | null;
+};
+
+/**
+ * Result of `test_capture_rules`
+ */
+export interface RuleVerdict {
+  /** Names of the rules that matched */
+  matched: string[];
+  skip: boolean;
+  /** The text with matches masked, if a redacting rule matched */
+  redacted: string | null;
+  expireAfterSecs: number | null;
+  maxPastes: number | null;
+  clearAfterSecs: number | null;
+}
+
+/**
+ * Clipboard monitoring state, from `monitoring_status`, `pause_monitoring` and
+ * `resume_monitoring` (and the `monitoring-status-changed` event)
+ */
+export type MonitorStatus = 'running' | 'paused' | 'stopped';
+
 /**
  * Application settings
  */
@@ -30,9 +210,30 @@ export interface Settings {
   storeOriginalImage: boolean;
   maxPinnedInTray: number;
   maxRecentInTray: number;
+  traySortMode: SortMode;
   customDataPath: string | null;
   enableAutostart: boolean;
   locale: string;
+  transformRules: TransformRule[];
+  captureRules: CaptureRule[];
+  /** What happens to copies password managers mark as not to be recorded */
+  passwordManagerAction: CaptureRuleAction;
+  detectSensitive: boolean;
+  maskSensitive: boolean;
+  /** Clear the clipboard this many seconds after a sensitive clip is pasted */
+  clearSensitiveAfterSecs: number | null;
+  /** Apps whose copies are recorded; all when empty */
+  allowedApps: string[];
+  /** Apps whose copies are never recorded */
+  excludedApps: string[];
+  /** Also record selected text (the PRIMARY selection, Linux only) */
+  capturePrimary: boolean;
+  /** Keep the clipboard and the selection in sync (Linux only) */
+  syncSelections: boolean;
+  /** How long a selection must stay unchanged before it is recorded or synced */
+  selectionDebounceMs: number;
+  /** Keep copied contents available after the app they came from exits (X11; XWayland apps only on Wayland) */
+  persistClipboard: boolean;
 }
 
 /**